@@ -0,0 +1,264 @@
+use crate::{input::*, loading::AssetState, physics::Player};
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_egui::EguiContext;
+use bevy_rapier3d::prelude::*;
+
+/// Spawns the entity with `ChaseCamera`, moves it after the player, and lets the player orbit and zoom.
+///
+/// The cameras are its children, from cameras.glb in the game. The camera stays out of islands
+/// with a ray cast, so this needs `BoatPhysicsPlugin`.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .add_systems(Startup, spawn_chase_camera)
+            .add_systems(
+                Update,
+                (
+                    tag_scene_camera,
+                    control_camera,
+                    move_camera.after(control_camera),
+                )
+                    .run_if(in_state(AssetState::Loaded)),
+            );
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Chase,
+    /// the view from cameras.glb, following the boat without rotating
+    TopDown,
+}
+
+/// follows the player from behind, and can be orbited and zoomed
+#[derive(Component)]
+pub struct ChaseCamera {
+    pub mode: CameraMode,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// height above the boat the camera looks at
+    pub height: f32,
+    /// how quickly the camera catches up with the boat, higher is snappier
+    pub smoothing: f32,
+    /// seconds of travel ahead of the boat to look at
+    pub look_ahead: f32,
+    /// orbit around the boat in radians, 0 is straight behind
+    pub yaw: f32,
+    /// orbit above the boat in radians, 0 is level with the focus point
+    pub pitch: f32,
+    /// radians per pixel of mouse movement
+    pub orbit_speed: f32,
+    pub zoom_speed: f32,
+}
+
+impl Default for ChaseCamera {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::default(),
+            distance: 15.,
+            min_distance: 4.,
+            max_distance: 60.,
+            height: 2.,
+            smoothing: 5.,
+            look_ahead: 0.5,
+            yaw: 0.,
+            pitch: 0.35,
+            orbit_speed: 0.005,
+            zoom_speed: 2.,
+        }
+    }
+}
+
+/// the camera from cameras.glb, and where it was placed in the scene
+#[derive(Component)]
+pub struct SceneCamera {
+    original: Transform,
+}
+
+/// keep the camera this far in front of any island it would otherwise be inside
+const COLLISION_MARGIN: f32 = 0.5;
+/// radians per second when orbiting with a stick held all the way
+const STICK_ORBIT_SPEED: f32 = 2.;
+
+pub fn spawn_chase_camera(mut cmd: Commands) {
+    cmd.spawn((ChaseCamera::default(), SpatialBundle::default()));
+}
+
+pub fn tag_scene_camera(
+    mut cmd: Commands,
+    cameras: Query<(Entity, &Transform), Added<Camera>>,
+    parents: Query<&Parent>,
+    chase_cameras: Query<(), With<ChaseCamera>>,
+) {
+    for (entity, trans) in cameras.iter() {
+        if parents
+            .iter_ancestors(entity)
+            .any(|e| chase_cameras.contains(e))
+        {
+            cmd.entity(entity).insert(SceneCamera { original: *trans });
+        }
+    }
+}
+
+pub fn control_camera(
+    actions: Res<ActionState>,
+    time: Res<Time>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut egui: Query<&mut EguiContext, With<PrimaryWindow>>,
+    mut camera: Query<&mut ChaseCamera>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+    // there is no egui context when the debug panel is left out
+    let over_ui = egui
+        .get_single_mut()
+        .is_ok_and(|mut ctx| ctx.get_mut().wants_pointer_input());
+
+    if actions.just_pressed(Action::ToggleCamera) {
+        camera.mode = match camera.mode {
+            CameraMode::Chase => CameraMode::TopDown,
+            CameraMode::TopDown => CameraMode::Chase,
+        };
+    }
+
+    let mut orbit = Vec2::new(
+        actions.axis(Action::OrbitRight, Action::OrbitLeft),
+        actions.axis(Action::OrbitUp, Action::OrbitDown),
+    ) * STICK_ORBIT_SPEED
+        * time.delta_seconds();
+    let mut zoom = actions.axis(Action::ZoomOut, Action::ZoomIn) * time.delta_seconds();
+    for motion in mouse_motion.iter() {
+        if mouse_buttons.pressed(MouseButton::Right) && !over_ui {
+            orbit += motion.delta * camera.orbit_speed;
+        }
+    }
+    for wheel in mouse_wheel.iter() {
+        if !over_ui {
+            zoom -= wheel.y.signum() * 0.1;
+        }
+    }
+
+    camera.yaw -= orbit.x;
+    camera.pitch = (camera.pitch + orbit.y).clamp(-0.2, 1.5);
+    camera.distance = (camera.distance * (1. + zoom * camera.zoom_speed))
+        .clamp(camera.min_distance, camera.max_distance);
+}
+
+pub fn move_camera(
+    time: Res<Time>,
+    rapier: Res<RapierContext>,
+    player: Query<(Entity, &Transform, &Velocity), With<Player>>,
+    mut camera: Query<(&mut Transform, &ChaseCamera), Without<Player>>,
+    mut scene_camera: Query<
+        (&mut Transform, &SceneCamera),
+        (Without<ChaseCamera>, Without<Player>),
+    >,
+) {
+    let (Ok((player, player_trans, player_vel)), Ok((mut trans, chase))) =
+        (player.get_single(), camera.get_single_mut())
+    else {
+        return;
+    };
+
+    if chase.mode == CameraMode::TopDown {
+        *trans = Transform::from_xyz(player_trans.translation.x, 0., player_trans.translation.z);
+        for (mut scene_trans, scene_camera) in scene_camera.iter_mut() {
+            *scene_trans = scene_camera.original;
+        }
+        return;
+    }
+    for (mut scene_trans, _) in scene_camera.iter_mut() {
+        *scene_trans = Transform::IDENTITY;
+    }
+
+    let forward = player_trans.forward();
+    let boat_yaw = f32::atan2(-forward.x, -forward.z);
+    let travel = Vec3::new(player_vel.linvel.x, 0., player_vel.linvel.z);
+    let focus = player_trans.translation + Vec3::Y * chase.height + travel * chase.look_ahead;
+
+    let orbit = Quat::from_rotation_y(boat_yaw + chase.yaw) * Quat::from_rotation_x(-chase.pitch);
+    let direction = orbit * Vec3::Z;
+
+    // pull the camera in front of islands, so they never block the view of the boat
+    let filter = QueryFilter::only_fixed()
+        .exclude_sensors()
+        .exclude_rigid_body(player);
+    let distance = match rapier.cast_ray(focus, direction, chase.distance, true, filter) {
+        Some((_, toi)) => (toi - COLLISION_MARGIN).max(0.),
+        None => chase.distance,
+    };
+    let target = focus + direction * distance;
+
+    let blend = 1. - (-chase.smoothing * time.delta_seconds()).exp();
+    trans.translation = trans.translation.lerp(target, blend);
+    trans.look_at(focus, Vec3::Y);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::BoatPhysicsPlugin;
+    use bevy::{
+        gltf::GltfPlugin, input::InputPlugin, scene::ScenePlugin, time::TimeUpdateStrategy,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn camera_moves_behind_boat() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            ScenePlugin,
+            GltfPlugin::default(),
+            BoatPhysicsPlugin,
+            CameraPlugin,
+        ))
+        .add_asset::<Mesh>()
+        .add_state::<AssetState>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            1. / 60.,
+        )));
+        app.world
+            .resource_mut::<NextState<AssetState>>()
+            .set(AssetState::Loaded);
+        app.update();
+        // turn the boat away from the default direction, so the camera has to follow it around
+        app.world
+            .query_filtered::<&mut Transform, With<Player>>()
+            .single_mut(&mut app.world)
+            .rotate_y(2.);
+        for _ in 0..120 {
+            app.update();
+        }
+
+        let player = *app
+            .world
+            .query_filtered::<&Transform, With<Player>>()
+            .single(&app.world);
+        let camera = app
+            .world
+            .query_filtered::<&Transform, With<ChaseCamera>>()
+            .single(&app.world);
+        let behind = (camera.translation - player.translation) * Vec3::new(1., 0., 1.);
+        let forward = player.forward() * Vec3::new(1., 0., 1.);
+        assert!(
+            behind.normalize().dot(forward.normalize()) < -0.9,
+            "camera at {} is not behind the boat at {} facing {forward}",
+            camera.translation,
+            player.translation
+        );
+    }
+}
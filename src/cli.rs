@@ -0,0 +1,172 @@
+use crate::{config::CONFIG_NAME, loading::*, physics::Player, utils::SimulationTime};
+use bevy::{app::AppExit, prelude::*, window::WindowMode};
+use bevy_rapier3d::prelude::Velocity;
+
+const USAGE: &str = "usage: seilespill [options]
+
+options:
+    --config <path>     the config file to use, relative to the assets folder
+    --preset <name>     use a preset instead of the config file
+    --windowed          run in a window, the default
+    --fullscreen        run in fullscreen
+    --physics-debug     draw the colliders from the start
+    --headless          run without a window, a GPU or the debug panel
+    --seconds <n>       exit after simulating this many seconds
+    --help              show this message";
+
+/// options given on the command line
+#[derive(Resource, Clone, Debug)]
+pub struct Args {
+    /// the config file instead of the one in the manifest
+    pub config: Option<String>,
+    pub preset: Option<String>,
+    pub window_mode: WindowMode,
+    pub physics_debug: bool,
+    pub headless: bool,
+    pub seconds: Option<f32>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            config: None,
+            preset: None,
+            window_mode: WindowMode::Windowed,
+            physics_debug: false,
+            headless: false,
+            seconds: None,
+        }
+    }
+}
+
+/// why the program should not run with the arguments it was given
+#[derive(Debug, PartialEq)]
+pub enum ArgsError {
+    /// `--help` was given, so the usage is shown instead
+    Help,
+    Invalid(String),
+}
+
+impl Args {
+    /// reads the arguments the program was started with, and exits with the usage if they are wrong
+    pub fn from_env() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(args) => args,
+            Err(ArgsError::Help) => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            Err(ArgsError::Invalid(e)) => {
+                eprintln!("{e}\n\n{USAGE}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        use ArgsError::*;
+
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| Invalid(format!("{arg} needs a value")))
+            };
+            match arg.as_str() {
+                "--config" => parsed.config = Some(value()?),
+                "--preset" => parsed.preset = Some(value()?),
+                "--windowed" => parsed.window_mode = WindowMode::Windowed,
+                // borderless, since exclusive fullscreen changes the resolution of the monitor
+                "--fullscreen" => parsed.window_mode = WindowMode::BorderlessFullscreen,
+                "--physics-debug" => parsed.physics_debug = true,
+                "--headless" => parsed.headless = true,
+                "--seconds" => {
+                    let seconds = value()?;
+                    parsed.seconds =
+                        Some(seconds.parse().map_err(|_| {
+                            Invalid(format!("'{seconds}' is not a number of seconds"))
+                        })?);
+                }
+                "--help" | "-h" => return Err(Help),
+                _ => return Err(Invalid(format!("unknown argument '{arg}'"))),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// where the config is read from and saved to
+    pub fn config_path(&self) -> &str {
+        self.config.as_deref().unwrap_or(CONFIG_NAME)
+    }
+}
+
+/// stops the program once `--seconds` have been simulated, and logs where the player ended up
+///
+/// The physics only runs once the vital assets are loaded, so loading does not count.
+pub fn exit_after_seconds(
+    args: Res<Args>,
+    sim_time: Res<SimulationTime>,
+    player: Query<(&Transform, &Velocity), With<Player>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(seconds) = args.seconds else {
+        return;
+    };
+    if sim_time.elapsed < seconds {
+        return;
+    }
+    if let Ok((trans, vel)) = player.get_single() {
+        info!(
+            "after {:.2} s the player is at {} moving at {}",
+            sim_time.elapsed, trans.translation, vel.linvel
+        );
+    }
+    exit.send(AppExit);
+}
+
+/// nobody can press retry without a window, so a headless run fails right away
+pub fn exit_on_load_failure(failures: Res<LoadFailures>) {
+    for failure in failures.failures.iter() {
+        error!("could not load '{}': {}", failure.path, failure.reason);
+    }
+    std::process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_values() {
+        let args = parse(&["--config", "x", "--seconds", "3"]).unwrap();
+        assert_eq!(args.config.as_deref(), Some("x"));
+        assert_eq!(args.config_path(), "x");
+        assert_eq!(args.seconds, Some(3.));
+        assert_eq!(args.preset, None);
+        assert!(!args.headless);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(
+            parse(&["--seconds", "3", "--config"]).unwrap_err(),
+            ArgsError::Invalid("--config needs a value".into())
+        );
+        assert_eq!(
+            parse(&["--seconds", "soon"]).unwrap_err(),
+            ArgsError::Invalid("'soon' is not a number of seconds".into())
+        );
+        assert_eq!(
+            parse(&["--fast"]).unwrap_err(),
+            ArgsError::Invalid("unknown argument '--fast'".into())
+        );
+        assert_eq!(
+            parse(&["--headless", "--help"]).unwrap_err(),
+            ArgsError::Help
+        );
+    }
+}
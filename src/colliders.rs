@@ -0,0 +1,320 @@
+use bevy::{
+    ecs::system::SystemParam,
+    gltf::{Gltf, GltfMesh, GltfNode},
+    prelude::*,
+    render::mesh::VertexAttributeValues,
+};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Spawns colliders for the named nodes in a glTF file, with the shape given by the end of the name.
+///
+/// - `-trimesh`: triangle mesh, for static geometry like islands
+/// - `-convex`: convex hull of the mesh
+/// - `-vhacd`: convex decomposition of the mesh, for dynamic bodies that are not convex
+/// - `-cuboid`, `-ball`, `-capsule`, `-cylinder`: fitted to the bounds of the mesh
+/// - `-sensor`: after any of the above makes it a sensor, on its own it is a convex sensor
+///
+/// A node named `rock-3-convex` goes on the entity with `ColliderName("rock-3")` if there is one,
+/// and on a new fixed body where the node is otherwise.
+/// Trimeshes going on a dynamic body are decomposed instead, since they have no volume.
+pub struct GltfColliderPlugin;
+
+impl Plugin for GltfColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DecompositionSettings>()
+            .add_systems(Update, spawn_gltf_colliders);
+    }
+}
+
+/// the glTF file to take colliders from, they are spawned once it is loaded
+#[derive(Resource)]
+pub struct GltfColliderSource(pub Handle<Gltf>);
+
+/// gives this entity the collider from the node with this name, without the suffix
+#[derive(Component)]
+pub struct ColliderName(pub String);
+
+/// added to every entity that got a collider from the glTF file
+#[derive(Component)]
+pub struct GltfCollider {
+    pub name: String,
+    pub shape: ColliderShape,
+}
+
+/// how finely `-vhacd` meshes are split into convex parts, read when the colliders are spawned
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+#[serde(default)]
+pub struct DecompositionSettings {
+    /// voxels along the longest side of the mesh
+    pub resolution: u32,
+    /// how far a part may be from convex before it is split, from 0 to 1
+    pub concavity: f32,
+    pub max_hulls: u32,
+}
+
+impl Default for DecompositionSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 64,
+            concavity: 0.01,
+            max_hulls: 16,
+        }
+    }
+}
+
+impl DecompositionSettings {
+    pub fn parameters(&self) -> VHACDParameters {
+        VHACDParameters {
+            resolution: self.resolution,
+            concavity: self.concavity,
+            max_convex_hulls: self.max_hulls,
+            ..default()
+        }
+    }
+}
+
+/// why a collider could not be made from a glTF node
+#[derive(Debug)]
+pub enum ColliderError {
+    NoMesh,
+    NoPrimitives,
+    MeshNotLoaded,
+    NoPositions,
+    NoTriangles,
+}
+
+impl fmt::Display for ColliderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColliderError::NoMesh => write!(f, "the node has no mesh"),
+            ColliderError::NoPrimitives => write!(f, "the mesh has no primitives"),
+            ColliderError::MeshNotLoaded => write!(f, "the mesh is not loaded"),
+            ColliderError::NoPositions => write!(f, "the mesh has no vertex positions"),
+            ColliderError::NoTriangles => write!(f, "the mesh has no triangles"),
+        }
+    }
+}
+
+/// the mesh of the first primitive in a glTF node
+pub fn node_mesh<'a>(
+    node: &GltfNode,
+    assets_gltf_mesh: &Assets<GltfMesh>,
+    assets_mesh: &'a Assets<Mesh>,
+) -> Result<&'a Mesh, ColliderError> {
+    let gltf_mesh = node
+        .mesh
+        .as_ref()
+        .and_then(|h| assets_gltf_mesh.get(h))
+        .ok_or(ColliderError::NoMesh)?;
+    let primitive = gltf_mesh
+        .primitives
+        .first()
+        .ok_or(ColliderError::NoPrimitives)?;
+    assets_mesh
+        .get(&primitive.mesh)
+        .ok_or(ColliderError::MeshNotLoaded)
+}
+
+/// axis aligned bounding box of the vertices in a mesh
+pub fn mesh_bounds(mesh: &Mesh) -> Result<(Vec3, Vec3), ColliderError> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return Err(ColliderError::NoPositions);
+    };
+    positions
+        .iter()
+        .map(|p| Vec3::from(*p))
+        .fold(None, |bounds, p| match bounds {
+            None => Some((p, p)),
+            Some((min, max)) => Some((min.min(p), max.max(p))),
+        })
+        .ok_or(ColliderError::NoPositions)
+}
+
+/// simple shapes that are fitted to the bounding box of a mesh
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrimitiveShape {
+    Cylinder,
+    Ball,
+    Cuboid,
+    Capsule,
+}
+
+impl PrimitiveShape {
+    /// a collider filling the box from `min` to `max`, centered at the origin
+    pub fn fit(&self, min: Vec3, max: Vec3) -> Collider {
+        let half = (max - min) / 2.;
+        match self {
+            PrimitiveShape::Cylinder => Collider::cylinder(half.y, half.x.max(half.z)),
+            PrimitiveShape::Ball => Collider::ball(half.max_element()),
+            PrimitiveShape::Cuboid => Collider::cuboid(half.x, half.y, half.z),
+            PrimitiveShape::Capsule => {
+                // the capsule lies along the longest side, and is as thick as the widest of the others
+                let longest = half.max_element();
+                if longest == half.x {
+                    let radius = half.y.max(half.z);
+                    Collider::capsule_x((half.x - radius).max(0.), radius)
+                } else if longest == half.y {
+                    let radius = half.x.max(half.z);
+                    Collider::capsule_y((half.y - radius).max(0.), radius)
+                } else {
+                    let radius = half.x.max(half.y);
+                    Collider::capsule_z((half.z - radius).max(0.), radius)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColliderShape {
+    TriMesh,
+    Convex,
+    ConvexDecomposition,
+    Primitive(PrimitiveShape),
+}
+
+impl ColliderShape {
+    /// splits a node name like `rock-3-convex-sensor` into `rock-3`, the shape and whether it is a sensor
+    pub fn from_name(name: &str) -> Option<(&str, Self, bool)> {
+        let (name, sensor) = match name.strip_suffix("-sensor") {
+            Some(stripped) => (stripped, true),
+            None => (name, false),
+        };
+        [
+            ("-trimesh", ColliderShape::TriMesh),
+            ("-convex", ColliderShape::Convex),
+            ("-vhacd", ColliderShape::ConvexDecomposition),
+            (
+                "-cylinder",
+                ColliderShape::Primitive(PrimitiveShape::Cylinder),
+            ),
+            ("-ball", ColliderShape::Primitive(PrimitiveShape::Ball)),
+            ("-cuboid", ColliderShape::Primitive(PrimitiveShape::Cuboid)),
+            (
+                "-capsule",
+                ColliderShape::Primitive(PrimitiveShape::Capsule),
+            ),
+        ]
+        .into_iter()
+        .find_map(|(suffix, shape)| name.strip_suffix(suffix).map(|stripped| (stripped, shape)))
+        .map(|(stripped, shape)| (stripped, shape, sensor))
+        .or(sensor.then_some((name, ColliderShape::Convex, true)))
+    }
+
+    /// a collider for the mesh, and where its center is in the space of the mesh
+    pub fn collider(
+        &self,
+        mesh: &Mesh,
+        decomposition: &DecompositionSettings,
+    ) -> Result<(Vec3, Collider), ColliderError> {
+        let computed = match self {
+            ColliderShape::TriMesh => ComputedColliderShape::TriMesh,
+            ColliderShape::Convex => ComputedColliderShape::ConvexHull,
+            ColliderShape::ConvexDecomposition => {
+                ComputedColliderShape::ConvexDecomposition(decomposition.parameters())
+            }
+            ColliderShape::Primitive(primitive) => {
+                let (min, max) = mesh_bounds(mesh)?;
+                return Ok(((min + max) / 2., primitive.fit(min, max)));
+            }
+        };
+        Collider::from_bevy_mesh(mesh, &computed)
+            .map(|collider| (Vec3::ZERO, collider))
+            .ok_or(ColliderError::NoTriangles)
+    }
+}
+
+#[derive(SystemParam)]
+struct GltfAssets<'w> {
+    gltf: Res<'w, Assets<Gltf>>,
+    nodes: Res<'w, Assets<GltfNode>>,
+    meshes: Res<'w, Assets<GltfMesh>>,
+    mesh: Res<'w, Assets<Mesh>>,
+}
+
+fn spawn_gltf_colliders(
+    mut cmd: Commands,
+    mut done: Local<bool>,
+    source: Option<Res<GltfColliderSource>>,
+    decomposition: Res<DecompositionSettings>,
+    assets: GltfAssets,
+    targets: Query<(Entity, &ColliderName, Option<&RigidBody>)>,
+) {
+    if *done {
+        return;
+    }
+    let Some(gltf) = source.and_then(|source| assets.gltf.get(&source.0)) else {
+        return;
+    };
+
+    for (node_name, handle) in gltf.named_nodes.iter() {
+        let Some((name, shape, sensor)) = ColliderShape::from_name(node_name) else {
+            continue;
+        };
+        let Some(node) = assets.nodes.get(handle) else {
+            continue;
+        };
+        let target = targets.iter().find(|(_, target, _)| target.0 == name);
+        let shape = match target {
+            Some((_, _, Some(RigidBody::Dynamic))) if shape == ColliderShape::TriMesh => {
+                warn!("'{node_name}' is on a dynamic body, using a convex decomposition instead");
+                ColliderShape::ConvexDecomposition
+            }
+            _ => shape,
+        };
+        let (offset, collider) = match node_mesh(node, &assets.meshes, &assets.mesh)
+            .and_then(|mesh| shape.collider(mesh, &decomposition))
+        {
+            Ok(v) => v,
+            Err(e) => {
+                error!("could not make a collider from '{node_name}': {e}");
+                continue;
+            }
+        };
+
+        let info = GltfCollider {
+            name: name.into(),
+            shape,
+        };
+        let mut entity = match target {
+            // the entity has its own transform, so the offset goes into the shape
+            Some((entity, ..)) => {
+                let collider = if offset == Vec3::ZERO {
+                    collider
+                } else {
+                    Collider::compound(vec![(offset, Quat::IDENTITY, collider)])
+                };
+                let mut entity = cmd.entity(entity);
+                entity.insert((info, collider));
+                entity
+            }
+            None => cmd.spawn((
+                info,
+                Name::new(name.to_owned()),
+                RigidBody::Fixed,
+                TransformBundle::from(node.transform * Transform::from_translation(offset)),
+                collider,
+            )),
+        };
+        if sensor {
+            entity.insert(Sensor);
+        }
+    }
+
+    for (_, target, _) in targets.iter() {
+        if !gltf
+            .named_nodes
+            .keys()
+            .filter_map(|k| ColliderShape::from_name(k))
+            .any(|(name, ..)| name == target.0)
+        {
+            warn!("no collider named '{}' in the glTF file", target.0);
+        }
+    }
+
+    *done = true;
+}
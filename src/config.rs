@@ -0,0 +1,484 @@
+use crate::{
+    cli::Args, colliders::DecompositionSettings, custom_assets::JsonAssetPlugin,
+    inspector::FieldHint, loading::*, migration::migrate_config, presets::*, sailing::Wind,
+    storage::*, water::Wave,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Loads the tunable values from `config.json`, or the saved copy, once the vital assets are loaded.
+///
+/// Changes are announced with `ConfigEvent`, and the file is reloaded when it changes on disk.
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(
+            JsonAssetPlugin::<ConfigValues>::new(&["json"]).with_preprocess(migrate_config),
+        )
+        .add_event::<ConfigEvent>()
+        .init_resource::<Config>()
+        .init_resource::<Storage>()
+        .init_resource::<Presets>()
+        .init_resource::<Args>()
+        .add_systems(
+            OnEnter(AssetState::Loaded),
+            (load_config, apply_preset_arg.after(load_config)).in_set(ConfigSet),
+        )
+        .add_systems(
+            Update,
+            (save_config, reload_config).run_if(in_state(AssetState::Loaded)),
+        );
+    }
+}
+
+/// the config is read in this set when the vital assets are loaded,
+/// systems that need the values it ends up with go after it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConfigSet;
+
+#[derive(Event)]
+pub enum ConfigEvent {
+    /// the config needs to save
+    Save,
+    /// the config is changed, in these sections
+    Changed(Vec<ConfigSection>),
+}
+
+impl ConfigEvent {
+    pub fn changed(&self, section: ConfigSection) -> bool {
+        matches!(self, ConfigEvent::Changed(sections) if sections.contains(&section))
+    }
+}
+
+/// parts of the config that systems can react to separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSection {
+    Physics,
+    Lights,
+    Water,
+    Wind,
+    Decomposition,
+}
+
+impl ConfigSection {
+    pub const ALL: [ConfigSection; 5] = [
+        ConfigSection::Physics,
+        ConfigSection::Lights,
+        ConfigSection::Water,
+        ConfigSection::Wind,
+        ConfigSection::Decomposition,
+    ];
+}
+
+#[derive(Resource)]
+pub struct Config {
+    pub saved: bool,
+    /// the file changed while there were unsaved edits, so it was not used
+    pub diverged: bool,
+    /// the next change to the file replaces any unsaved edits
+    pub reloading: bool,
+    pub values: ConfigValues,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            saved: true,
+            diverged: false,
+            reloading: false,
+            values: ConfigValues::default(),
+        }
+    }
+}
+
+pub const CONFIG_NAME: &str = "config.json";
+
+/// the saved config, which is `CONFIG_NAME` unless another file is given on the command line
+#[derive(SystemParam)]
+pub struct ConfigFile<'w> {
+    storage: Res<'w, Storage>,
+    args: Res<'w, Args>,
+    asset_server: Res<'w, AssetServer>,
+}
+
+impl ConfigFile<'_> {
+    /// the saved values, or `None` if nothing is saved or it can not be read
+    pub fn read(&self) -> Option<ConfigValues> {
+        match self.storage.read(self.args.config_path()) {
+            Ok(Some(text)) => serde_json::from_str(&text)
+                .and_then(|value| serde_json::from_value(migrate_config(value)))
+                .map_err(|e| warn!("the saved config could not be read: {e}"))
+                .ok(),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("could not read the saved config: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn write(&self, values: &ConfigValues) -> Result<(), StorageError> {
+        self.storage
+            .write(self.args.config_path(), &json!(values).to_string())
+    }
+
+    /// loads the config asset again, `reload_config` reads the saved copy or the asset when it is done
+    pub fn reload(&self) {
+        self.asset_server.reload_asset(self.args.config_path());
+    }
+}
+
+/// bump this and add a function to `MIGRATIONS` when fields are renamed or change meaning
+pub const CONFIG_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Reflect, bevy::reflect::TypeUuid)]
+#[uuid = "413be529-bfeb-41b3-9db0-4b8b380a2c46"]
+#[serde(default)]
+pub struct ConfigValues {
+    #[reflect(ignore)]
+    pub version: u32,
+    pub drag_forward_c: f32,
+    pub drag_lateral_c: f32,
+    pub drag_vertical_c: f32,
+    pub avg_boat_height: f32,
+    pub floating_c: f32,
+    pub drag_ang_c: f32,
+    pub light_dir_color: Color,
+    pub light_amb_color: Color,
+    pub light_dir_lum: f32,
+    pub light_amb_lum: f32,
+    pub waves: Vec<Wave>,
+    pub wind: Wind,
+    pub decomposition: DecompositionSettings,
+}
+
+impl Default for ConfigValues {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            drag_forward_c: 1.,
+            drag_lateral_c: 15.,
+            drag_vertical_c: 5.,
+            avg_boat_height: 1.,
+            floating_c: 2.,
+            drag_ang_c: 3.,
+            light_dir_color: Color::rgb(0.98, 0.97, 0.8),
+            light_dir_lum: 50_000.,
+            light_amb_color: Color::rgb(0.5, 0.5, 0.8),
+            light_amb_lum: 1.,
+            waves: vec![
+                Wave {
+                    amplitude: 0.3,
+                    wavelength: 20.,
+                    direction: 0.,
+                },
+                Wave {
+                    amplitude: 0.15,
+                    wavelength: 9.,
+                    direction: 0.7,
+                },
+                Wave {
+                    amplitude: 0.08,
+                    wavelength: 4.,
+                    direction: -1.2,
+                },
+            ],
+            wind: Wind::default(),
+            decomposition: DecompositionSettings::default(),
+        }
+    }
+}
+
+/// slider ranges for the debug panel, fields without one get a drag value
+pub const CONFIG_HINTS: &[FieldHint] = {
+    use std::f64::consts::PI;
+    &[
+        FieldHint::range("drag_forward_c", 0., 5.),
+        FieldHint::range("drag_lateral_c", 0., 30.),
+        FieldHint::range("drag_vertical_c", 0., 20.),
+        FieldHint::range("avg_boat_height", 0., 5.),
+        FieldHint::range("floating_c", 0., 5.),
+        FieldHint::range("drag_ang_c", 0., 10.),
+        FieldHint::range("light_dir_lum", 0., 100_000.),
+        FieldHint::range("light_amb_lum", 0., 3.),
+        FieldHint::range("waves.amplitude", 0., 2.),
+        FieldHint::range("waves.wavelength", 0.5, 50.),
+        FieldHint::range("waves.direction", -PI, PI),
+        FieldHint::range("wind.direction", -PI, PI),
+        FieldHint::range("wind.strength", 0., 25.),
+        FieldHint::range("wind.gust", 0., 1.),
+        FieldHint::range("wind.gust_period", 1., 60.),
+        FieldHint::range("decomposition.resolution", 16., 256.),
+        FieldHint::logarithmic("decomposition.concavity", 0.001, 0.1),
+        FieldHint::range("decomposition.max_hulls", 1., 64.),
+    ]
+};
+
+impl ConfigValues {
+    /// the sections where `other` is different from this
+    pub fn diff(&self, other: &Self) -> Vec<ConfigSection> {
+        ConfigSection::ALL
+            .into_iter()
+            .filter(|section| match section {
+                ConfigSection::Physics => {
+                    (
+                        self.drag_forward_c,
+                        self.drag_lateral_c,
+                        self.drag_vertical_c,
+                        self.avg_boat_height,
+                        self.floating_c,
+                        self.drag_ang_c,
+                    ) != (
+                        other.drag_forward_c,
+                        other.drag_lateral_c,
+                        other.drag_vertical_c,
+                        other.avg_boat_height,
+                        other.floating_c,
+                        other.drag_ang_c,
+                    )
+                }
+                ConfigSection::Lights => {
+                    (
+                        self.light_dir_color,
+                        self.light_amb_color,
+                        self.light_dir_lum,
+                        self.light_amb_lum,
+                    ) != (
+                        other.light_dir_color,
+                        other.light_amb_color,
+                        other.light_dir_lum,
+                        other.light_amb_lum,
+                    )
+                }
+                ConfigSection::Water => self.waves != other.waves,
+                ConfigSection::Wind => self.wind != other.wind,
+                ConfigSection::Decomposition => self.decomposition != other.decomposition,
+            })
+            .collect()
+    }
+}
+
+/// uses the player's saved copy of the config, or the one shipped with the game
+pub fn load_config(
+    mut config: ResMut<Config>,
+    mut writer: EventWriter<ConfigEvent>,
+    config_asset: Res<Assets<ConfigValues>>,
+    assets: Res<ManifestAssets>,
+    file: ConfigFile,
+) {
+    match file.read().or_else(|| {
+        assets
+            .get::<ConfigValues>("config")
+            .and_then(|h| config_asset.get(&h).cloned())
+    }) {
+        None => {
+            warn!("config not loaded");
+        }
+        Some(v) => {
+            config.values = v;
+        }
+    }
+    // make use the config values are used once loaded
+    writer.send(ConfigEvent::Changed(ConfigSection::ALL.to_vec()));
+}
+
+pub fn save_config(
+    mut config: ResMut<Config>,
+    mut events: EventReader<ConfigEvent>,
+    file: ConfigFile,
+) {
+    for _ in events.iter().filter(|e| matches!(e, ConfigEvent::Save)) {
+        match file.write(&config.values) {
+            Ok(_) => {
+                config.saved = true;
+                config.diverged = false;
+            }
+            Err(e) => error!("could not save config file: {e}"),
+        }
+    }
+}
+
+/// uses the config file when it changes on disk, unless that would throw away unsaved edits
+///
+/// Like `load_config`, the saved copy wins over the shipped one, so the web build keeps what the
+/// player saved in local storage.
+pub fn reload_config(
+    mut events: EventReader<AssetEvent<ConfigValues>>,
+    assets: Res<ManifestAssets>,
+    config_asset: Res<Assets<ConfigValues>>,
+    mut config: ResMut<Config>,
+    mut writer: EventWriter<ConfigEvent>,
+    file: ConfigFile,
+) {
+    for event in events.iter() {
+        let AssetEvent::Modified { handle } = event else {
+            continue;
+        };
+        if assets.get::<ConfigValues>("config").as_ref() != Some(handle) {
+            continue;
+        }
+        let Some(values) = file.read().or_else(|| config_asset.get(handle).cloned()) else {
+            continue;
+        };
+        if config.saved || config.reloading {
+            info!("config reloaded");
+            let changes = config.values.diff(&values);
+            config.values = values;
+            config.saved = true;
+            config.diverged = false;
+            config.reloading = false;
+            writer.send(ConfigEvent::Changed(changes));
+        } else {
+            config.diverged = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    /// the config plugin with the shipped config already loaded, and nothing saved
+    fn config_app(shipped: ConfigValues) -> App {
+        let mut app = App::new();
+        app.insert_resource(Storage(Box::new(MemoryStorage::default())))
+            .add_plugins((MinimalPlugins, AssetPlugin::default(), ConfigPlugin))
+            .add_state::<AssetState>();
+        let handle = app
+            .world
+            .resource_mut::<Assets<ConfigValues>>()
+            .add(shipped);
+        app.insert_resource(ManifestAssets::new(vec![(
+            ManifestEntry {
+                name: "config".into(),
+                path: CONFIG_NAME.into(),
+                vital: false,
+            },
+            handle.clone_untyped(),
+        )]));
+        app.world
+            .resource_mut::<NextState<AssetState>>()
+            .set(AssetState::Loaded);
+        app
+    }
+
+    #[test]
+    fn loads_shipped_config() {
+        let shipped = ConfigValues {
+            drag_forward_c: 2.,
+            ..default()
+        };
+        let mut app = config_app(shipped);
+        app.update();
+
+        let config = app.world.resource::<Config>();
+        assert_eq!(config.values.drag_forward_c, 2.);
+        assert!(config.saved);
+        let events = app.world.resource::<Events<ConfigEvent>>();
+        assert!(events
+            .get_reader()
+            .iter(events)
+            .any(|e| e.changed(ConfigSection::Physics)));
+    }
+
+    #[test]
+    fn prefers_saved_config() {
+        let shipped = ConfigValues {
+            drag_forward_c: 2.,
+            ..default()
+        };
+        let saved = ConfigValues {
+            drag_forward_c: 3.,
+            ..default()
+        };
+        let mut app = config_app(shipped);
+        app.world
+            .resource::<Storage>()
+            .write(CONFIG_NAME, &json!(saved).to_string())
+            .unwrap();
+        app.update();
+
+        assert_eq!(app.world.resource::<Config>().values.drag_forward_c, 3.);
+    }
+
+    #[test]
+    fn reads_any_number_of_waves() {
+        let text = json!({
+            "version": CONFIG_VERSION,
+            "waves": [{ "amplitude": 0.5 }, { "wavelength": 3.0 }],
+        });
+        let values: ConfigValues = serde_json::from_value(migrate_config(text)).unwrap();
+
+        assert_eq!(values.waves.len(), 2);
+        assert_eq!(values.waves[0].amplitude, 0.5);
+        assert_eq!(values.waves[1].wavelength, 3.);
+        assert_eq!(
+            values.drag_forward_c,
+            ConfigValues::default().drag_forward_c
+        );
+    }
+
+    #[test]
+    fn reload_keeps_saved_config() {
+        let shipped = ConfigValues {
+            drag_forward_c: 2.,
+            ..default()
+        };
+        let saved = ConfigValues {
+            drag_forward_c: 3.,
+            ..default()
+        };
+        let mut app = config_app(shipped);
+        app.world
+            .resource::<Storage>()
+            .write(CONFIG_NAME, &json!(saved).to_string())
+            .unwrap();
+        app.update();
+
+        // the shipped file changes, which sends `AssetEvent::Modified`
+        let handle = app
+            .world
+            .resource::<ManifestAssets>()
+            .get::<ConfigValues>("config")
+            .unwrap();
+        app.world
+            .resource_mut::<Assets<ConfigValues>>()
+            .get_mut(&handle)
+            .unwrap()
+            .drag_forward_c = 4.;
+        app.update();
+        app.update();
+
+        assert_eq!(app.world.resource::<Config>().values.drag_forward_c, 3.);
+    }
+
+    #[test]
+    fn every_hint_names_a_number() {
+        /// follows `path` through structs, and into the first element of lists and arrays
+        fn resolves(value: &dyn Reflect, path: &[&str]) -> bool {
+            use bevy::reflect::ReflectRef;
+            let Some((name, rest)) = path.split_first() else {
+                return matches!(value.reflect_ref(), ReflectRef::Value(_));
+            };
+            match value.reflect_ref() {
+                ReflectRef::Struct(s) => s.field(name).is_some_and(|field| resolves(field, rest)),
+                ReflectRef::List(l) => l.get(0).is_some_and(|item| resolves(item, path)),
+                ReflectRef::Array(a) => a.get(0).is_some_and(|item| resolves(item, path)),
+                _ => false,
+            }
+        }
+
+        let values = ConfigValues::default();
+        for hint in CONFIG_HINTS {
+            let path: Vec<_> = hint.path.split('.').collect();
+            assert!(
+                resolves(&values, &path),
+                "the hint for '{}' does not name a number in the config",
+                hint.path
+            );
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use bevy::app::{App, Plugin};
 use bevy::asset::{AddAsset, Asset, AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use serde_json::Value;
 use std::marker::PhantomData;
 
 /// Plugin to load your asset type `A` from json files.
 pub struct JsonAssetPlugin<A> {
     extensions: Vec<&'static str>,
+    preprocess: fn(Value) -> Value,
     _marker: PhantomData<A>,
 }
 
@@ -15,6 +17,7 @@ where
     fn build(&self, app: &mut App) {
         app.add_asset::<A>().add_asset_loader(JsonAssetLoader::<A> {
             extensions: self.extensions.clone(),
+            preprocess: self.preprocess,
             _marker: PhantomData,
         });
     }
@@ -28,13 +31,21 @@ where
     pub fn new(extensions: &[&'static str]) -> Self {
         Self {
             extensions: extensions.to_owned(),
+            preprocess: |value| value,
             _marker: PhantomData,
         }
     }
+
+    /// Change the json before it is deserialized, for example to upgrade files from older versions.
+    pub fn with_preprocess(mut self, preprocess: fn(Value) -> Value) -> Self {
+        self.preprocess = preprocess;
+        self
+    }
 }
 
 struct JsonAssetLoader<A> {
     extensions: Vec<&'static str>,
+    preprocess: fn(Value) -> Value,
     _marker: PhantomData<A>,
 }
 
@@ -48,7 +59,8 @@ where
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
         Box::pin(async move {
-            let asset = serde_json::from_slice::<A>(bytes)?;
+            let value = serde_json::from_slice::<Value>(bytes)?;
+            let asset = serde_json::from_value::<A>((self.preprocess)(value))?;
             load_context.set_default_asset(LoadedAsset::new(asset));
             Ok(())
         })
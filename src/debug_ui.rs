@@ -0,0 +1,208 @@
+use crate::{
+    camera::*, cli::Args, config::*, dock::PlayerData, inspector::inspect, loading::AssetState,
+    physics::*, presets::*, sailing::Sail, storage::Storage, utils::length_xz,
+};
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use bevy_rapier3d::prelude::*;
+
+/// Windows for tuning the config, the boat and the camera while the game runs.
+///
+/// They are drawn on the primary window, if there is one. The colliders are drawn by Rapier,
+/// so this needs `BoatPhysicsPlugin`.
+#[derive(Default)]
+pub struct DebugUiPlugin {
+    /// draw the colliders from the start, they can be toggled in the panel
+    pub physics_debug: bool,
+}
+
+impl Plugin for DebugUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.add_plugins(RapierDebugRenderPlugin {
+            enabled: self.physics_debug,
+            ..default()
+        })
+        .init_resource::<Config>()
+        .add_event::<ConfigEvent>()
+        .init_resource::<Storage>()
+        .init_resource::<Presets>()
+        .init_resource::<Args>()
+        .init_resource::<PlayerData>()
+        .add_systems(
+            Update,
+            (update_ui, presets_ui.run_if(in_state(AssetState::Loaded)))
+                .run_if(any_with_component::<PrimaryWindow>()),
+        );
+    }
+}
+
+pub fn update_ui(
+    state: Res<State<AssetState>>,
+    mut contexts: EguiContexts,
+    mut writer_config: EventWriter<ConfigEvent>,
+    mut config: ResMut<Config>,
+    file: ConfigFile,
+    mut debug_mode: ResMut<DebugRenderContext>,
+    player_data: Res<PlayerData>,
+    mut player_query: Query<(&Velocity, &mut Propulsion, &Sail), With<Player>>,
+    mut camera_query: Query<&mut ChaseCamera>,
+) {
+    use egui::*;
+    Window::new("debug control panel").show(contexts.ctx_mut(), |ui| {
+        match state.get() {
+            AssetState::Loading => ui.label("loading assets for vital functions"),
+            AssetState::Loaded => ui.label(
+                "press arrow keys to move the boat, W and S to trim the sail, \
+                and hold the right mouse button to look around",
+            ),
+            AssetState::Failed => {
+                ui.label("vital assets failed to load, the reasons are listed on screen")
+            }
+        };
+        ui.separator();
+
+        let mut changed = false;
+        let before = config.values.clone();
+
+        ui.collapsing("physics", |ui| {
+            if let Ok((player_vel, mut propulsion, player_sail)) = player_query.get_single_mut() {
+                ui.add(Slider::new(&mut propulsion.thrust, 0.0..=50_000.).text("thrust"));
+                ui.add(Slider::new(&mut propulsion.reverse_ratio, 0.0..=1.).text("reverse ratio"));
+                ui.add(
+                    Slider::new(&mut propulsion.rudder_torque, 0.0..=10_000.).text("rudder torque"),
+                );
+                ui.label(format!(
+                    "player speed: {:.2}",
+                    length_xz(&player_vel.linvel)
+                ));
+                ui.label(format!("sail trim: {:.2}", player_sail.trim));
+            }
+
+            ui.checkbox(&mut debug_mode.enabled, "render bbox");
+
+            ui.label(format!("docking state: {:?}", player_data.dock_state));
+        });
+        ui.collapsing("config", |ui| {
+            changed = inspect(ui, &mut config.values, CONFIG_HINTS);
+            ui.label("the decomposition is used from the next start");
+        });
+        if let Ok(mut camera) = camera_query.get_single_mut() {
+            ui.collapsing("camera", |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut camera.mode, CameraMode::Chase, "chase");
+                    ui.radio_value(&mut camera.mode, CameraMode::TopDown, "top down");
+                });
+                let (min, max) = (camera.min_distance, camera.max_distance);
+                ui.add(Slider::new(&mut camera.distance, min..=max).text("distance"));
+                ui.add(Slider::new(&mut camera.height, 0.0..=10.).text("height"));
+                ui.add(Slider::new(&mut camera.smoothing, 0.5..=20.).text("smoothing"));
+                ui.add(Slider::new(&mut camera.look_ahead, 0.0..=3.).text("look ahead"));
+            });
+        }
+        // make sure config values is updated, and the file is saved
+        config.saved &= !changed;
+        if changed {
+            writer_config.send(ConfigEvent::Changed(before.diff(&config.values)));
+        }
+
+        if config.diverged {
+            ui.colored_label(
+                Color32::YELLOW,
+                "the config file changed on disk, reload to use it or save to overwrite it",
+            );
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !config.saved,
+                    if config.saved {
+                        Button::new("config saved")
+                    } else {
+                        Button::new("save config")
+                    },
+                )
+                .clicked()
+            {
+                writer_config.send(ConfigEvent::Save);
+            }
+            if ui.button("reload config").clicked() {
+                config.reloading = true;
+                file.reload();
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loading::ManifestAssets;
+    use bevy::{
+        gizmos::GizmoPlugin, gltf::GltfPlugin, input::InputPlugin, render::render_resource::Shader,
+        scene::ScenePlugin,
+    };
+    use bevy_egui::EguiContext;
+
+    #[test]
+    fn draws_on_primary_window() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            WindowPlugin::default(),
+            ScenePlugin,
+            GltfPlugin::default(),
+        ))
+        .add_asset::<Mesh>()
+        .add_asset::<Shader>()
+        .add_asset::<Image>()
+        .add_asset::<ConfigValues>()
+        .add_plugins((
+            GizmoPlugin,
+            BoatPhysicsPlugin,
+            CameraPlugin,
+            DebugUiPlugin::default(),
+        ))
+        .add_state::<AssetState>()
+        .insert_resource(ManifestAssets::new(Vec::new()));
+        app.world
+            .resource_mut::<NextState<AssetState>>()
+            .set(AssetState::Loaded);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        // the panel has the player and camera sections to draw
+        assert_eq!(
+            app.world
+                .query_filtered::<(), With<Player>>()
+                .iter(&app.world)
+                .count(),
+            1
+        );
+        assert_eq!(
+            app.world
+                .query_filtered::<(), With<ChaseCamera>>()
+                .iter(&app.world)
+                .count(),
+            1
+        );
+        let ctx = app
+            .world
+            .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+            .single_mut(&mut app.world)
+            .get_mut()
+            .clone();
+        for title in ["debug control panel", "presets"] {
+            let id = egui::Id::new(title);
+            assert!(
+                ctx.memory(|memory| memory.layer_ids().any(|layer| layer.id == id)),
+                "no '{title}' window"
+            );
+        }
+    }
+}
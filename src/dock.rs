@@ -1,33 +1,119 @@
+use crate::{colliders::*, input::*, loading::*, physics::*, utils::*};
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+/// Docks the player at ports, and shows the dock menu while docked.
+///
+/// Ports are sensors from the colliders named `dock-<id>`, so this needs `BoatPhysicsPlugin`.
+/// The menu shows the `DockCards` of the dock, which `TradePlugin` fills in, and sends a
+/// `TradeEvent` when a card with a deal is chosen.
+pub struct DockingPlugin;
+
+impl Plugin for DockingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DockEvent>()
+            .add_event::<TradeEvent>()
+            .init_resource::<PlayerData>()
+            .init_resource::<ActionState>()
+            .add_systems(
+                FixedUpdate,
+                // collision events are cleared every step, so they are read at the same rate
+                wire_sensor_events
+                    .after(PhysicsSet::Writeback)
+                    .run_if(in_state(AssetState::Loaded)),
+            )
+            .add_systems(Update, tag_docks)
+            .add_systems(
+                Update,
+                (
+                    wire_dock_events,
+                    dock_menu,
+                    navigate_cards,
+                    animate_cards.after(navigate_cards),
+                )
+                    .run_if(in_state(AssetState::Loaded)),
+            );
+    }
+}
+
+/// the trade one of the traders at a dock offers on their card
+#[derive(Clone, Copy, Debug)]
+pub struct Deal {
+    pub dock: Entity,
+    /// index into the traders of the port
+    pub trader: usize,
+}
+
+/// the player chose a card with this deal in the dock menu
+#[derive(Event)]
+pub struct TradeEvent(pub Deal);
 
 #[derive(Default)]
 pub struct Card {
     pub person_name: String,
     pub task: String,
+    /// the trade made when the card is chosen, nothing happens if `None`
+    pub deal: Option<Deal>,
+}
+
+/// a card in the dock menu, it grows when selected
+#[derive(Component)]
+pub struct CardNode {
+    /// position from the left in the dock menu
+    pub index: usize,
+    pub deal: Option<Deal>,
+    /// from 0, the size of a card that is not selected, to 1, the size of the selected card
+    pub grow: f32,
+}
+
+impl CardNode {
+    /// the size in percent of the space for the cards, and the paper color
+    fn look(&self) -> (Val, Val, Color) {
+        let lerp = |a: f32, b: f32| a + (b - a) * self.grow;
+        (
+            Val::Percent(lerp(40., 50.)),
+            Val::Percent(lerp(80., 100.)),
+            Color::rgb(lerp(0.9, 1.), lerp(0.75, 0.85), lerp(0.55, 0.63)),
+        )
+    }
 }
 
 impl Card {
-    pub fn spawn_node(&self, selected: bool, cmd: &mut ChildBuilder, font: Handle<Font>) {
+    pub fn spawn_node(
+        &self,
+        index: usize,
+        selected: bool,
+        cmd: &mut ChildBuilder,
+        font: Handle<Font>,
+    ) {
         let text_style = TextStyle {
             font,
             font_size: 18.,
             color: Color::BLACK,
         };
-        let color_paper = Color::rgb(1., 0.85, 0.63).into();
-        cmd.spawn(NodeBundle {
-            style: Style {
-                width: Val::Percent(if selected { 50. } else { 40. }),
-                height: Val::Percent(if selected { 100. } else { 80. }),
-                justify_content: JustifyContent::FlexStart,
-                align_items: AlignItems::Stretch,
-                flex_direction: FlexDirection::Column,
-                padding: UiRect::percent(2., 2., 2., 2.),
-                margin: UiRect::percent(0.5, 0.5, 0.5, 0.5),
+        let node = CardNode {
+            index,
+            deal: self.deal,
+            grow: if selected { 1. } else { 0. },
+        };
+        let (width, height, color_paper) = node.look();
+        cmd.spawn((
+            node,
+            ButtonBundle {
+                style: Style {
+                    width,
+                    height,
+                    justify_content: JustifyContent::FlexStart,
+                    align_items: AlignItems::Stretch,
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::percent(2., 2., 2., 2.),
+                    margin: UiRect::percent(0.5, 0.5, 0.5, 0.5),
+                    ..default()
+                },
+                background_color: color_paper.into(),
                 ..default()
             },
-            background_color: color_paper,
-            ..default()
-        })
+        ))
         .with_children(|cmd| {
             cmd.spawn(TextBundle::from_section(
                 self.person_name.clone(),
@@ -41,7 +127,366 @@ impl Card {
     }
 }
 
+#[derive(Event)]
+pub enum DockEvent {
+    Docking,
+    UnDocking,
+}
+
+/// a sensor around a port, from a collider named `dock-<id>` in bboxes.glb
 #[derive(Component)]
-struct Dock {
-    cards: Vec<Card>,
+pub struct Dock {
+    pub id: String,
+}
+
+#[derive(Component)]
+pub struct DockMenu {
+    /// the index of the selected card
+    pub selected: usize,
+}
+
+/// the cards shown in the dock menu while docked here
+#[derive(Component, Default)]
+pub struct DockCards(pub Vec<Card>);
+
+/// a line of text under the cards in the dock menu, for other plugins to fill in
+#[derive(Component)]
+pub struct DockStatus;
+
+#[derive(Default, Debug)]
+pub enum DockState {
+    #[default]
+    TooFar,
+    CloseTo(Entity),
+    DockedTo(Entity),
+}
+
+#[derive(Resource, Default)]
+pub struct PlayerData {
+    pub dock_state: DockState,
+}
+
+pub fn tag_docks(
+    mut cmd: Commands,
+    colliders: Query<(Entity, &GltfCollider), Added<GltfCollider>>,
+) {
+    for (entity, collider) in colliders.iter() {
+        let id = match collider.name.strip_prefix("dock-") {
+            Some(id) => id,
+            // bboxes.glb still names the dock cylinders after their islands
+            None if collider.name.starts_with("island-")
+                && collider.shape == ColliderShape::Primitive(PrimitiveShape::Cylinder) =>
+            {
+                &collider.name
+            }
+            None => continue,
+        };
+        debug!("spawning dock '{id}'");
+        cmd.entity(entity).insert((Dock { id: id.into() }, Sensor));
+    }
+}
+
+/// shown when a dock has no cards
+fn nobody_card() -> Card {
+    Card {
+        person_name: "Nobody".into(),
+        task: "There is nobody to trade with here.".into(),
+        deal: None,
+    }
+}
+
+pub fn dock_menu(
+    mut cmd: Commands,
+    mut dock_reader: EventReader<DockEvent>,
+    dock_menu: Query<Entity, With<DockMenu>>,
+    player_data: Res<PlayerData>,
+    dock_cards: Query<&DockCards>,
+    assets: Res<ManifestAssets>,
+) {
+    for event in dock_reader.iter() {
+        let font = assets.get::<Font>("font").unwrap_or_default();
+        match event {
+            DockEvent::Docking => {
+                let DockState::DockedTo(dock) = player_data.dock_state else {
+                    continue;
+                };
+                if !dock_menu.is_empty() {
+                    continue;
+                }
+                let nobody = [nobody_card()];
+                let cards = match dock_cards.get(dock) {
+                    Ok(DockCards(cards)) if !cards.is_empty() => cards.as_slice(),
+                    _ => &nobody,
+                };
+                let selected = cards.len() / 2;
+                cmd.spawn(DockMenu { selected })
+                    .insert(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(50.0),
+                            height: Val::Percent(35.0),
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(25.),
+                            top: Val::Percent(25.),
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|cmd| {
+                        cmd.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(75.),
+                                justify_content: JustifyContent::SpaceEvenly,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|cmd| {
+                            for (i, card) in cards.iter().enumerate() {
+                                card.spawn_node(i, i == selected, cmd, font.clone());
+                            }
+                        });
+                        cmd.spawn((
+                            DockStatus,
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 18.,
+                                    color: Color::WHITE,
+                                },
+                            ),
+                        ));
+                    });
+            }
+            DockEvent::UnDocking => {
+                if !dock_menu.is_empty() {
+                    cmd.entity(dock_menu.single()).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+/// moves the selection with the keyboard, a gamepad or the mouse, and makes the deal on a chosen card
+///
+/// Cancelling closes the menu, and it opens again by interacting while still docked.
+pub fn navigate_cards(
+    mut cmd: Commands,
+    actions: Res<ActionState>,
+    player_data: Res<PlayerData>,
+    mut menu: Query<(Entity, &mut DockMenu)>,
+    cards: Query<(&CardNode, Ref<Interaction>)>,
+    mut dock_writer: EventWriter<DockEvent>,
+    mut trade_writer: EventWriter<TradeEvent>,
+) {
+    let Ok((entity, mut menu)) = menu.get_single_mut() else {
+        if actions.just_pressed(Action::Interact)
+            && matches!(player_data.dock_state, DockState::DockedTo(_))
+        {
+            dock_writer.send(DockEvent::Docking);
+        }
+        return;
+    };
+    if actions.just_pressed(Action::Menu) {
+        cmd.entity(entity).despawn_recursive();
+        return;
+    }
+    let count = cards.iter().count();
+    if count == 0 {
+        return;
+    }
+
+    let mut chosen = actions.just_pressed(Action::Interact);
+    if actions.just_pressed(Action::NavigateLeft) {
+        menu.selected = menu.selected.saturating_sub(1);
+    }
+    if actions.just_pressed(Action::NavigateRight) {
+        menu.selected = (menu.selected + 1).min(count - 1);
+    }
+    for (card, interaction) in cards.iter().filter(|(_, i)| i.is_changed()) {
+        match *interaction {
+            Interaction::Hovered => menu.selected = card.index,
+            Interaction::Pressed => {
+                menu.selected = card.index;
+                chosen = true;
+            }
+            Interaction::None => {}
+        }
+    }
+
+    if chosen {
+        if let Some(deal) = cards
+            .iter()
+            .find(|(card, _)| card.index == menu.selected)
+            .and_then(|(card, _)| card.deal)
+        {
+            trade_writer.send(TradeEvent(deal));
+        }
+    }
+}
+
+/// grows the selected card and shrinks the others, a little each frame
+pub fn animate_cards(
+    time: Res<Time>,
+    menu: Query<&DockMenu>,
+    mut cards: Query<(&mut CardNode, &mut Style, &mut BackgroundColor)>,
+) {
+    /// how quickly the cards change size, higher is snappier
+    const SPEED: f32 = 12.;
+
+    let Ok(menu) = menu.get_single() else {
+        return;
+    };
+    let blend = 1. - (-SPEED * time.delta_seconds()).exp();
+    for (mut card, mut style, mut color) in cards.iter_mut() {
+        let target = if card.index == menu.selected { 1. } else { 0. };
+        if card.grow == target {
+            continue;
+        }
+        card.grow += (target - card.grow) * blend;
+        if (target - card.grow).abs() < 0.001 {
+            card.grow = target;
+        }
+        let (width, height, paper) = card.look();
+        style.width = width;
+        style.height = height;
+        color.0 = paper;
+    }
+}
+
+pub fn wire_dock_events(
+    player_query: Query<&Velocity, With<Player>>,
+    mut player_data: ResMut<PlayerData>,
+    mut dock_writer: EventWriter<DockEvent>,
+) {
+    const MAX_DOCK_VEL: f32 = 0.1;
+    const MIN_UNDOCK_VEL: f32 = 0.5;
+
+    let Ok(velocity) = player_query.get_single() else {
+        return;
+    };
+    let speed = length_xz(&velocity.linvel);
+    match player_data.dock_state {
+        DockState::TooFar => {}
+        DockState::CloseTo(dock) => {
+            if speed < MAX_DOCK_VEL {
+                player_data.dock_state = DockState::DockedTo(dock);
+                dock_writer.send(DockEvent::Docking);
+            }
+        }
+        DockState::DockedTo(dock) => {
+            if MIN_UNDOCK_VEL < speed {
+                player_data.dock_state = DockState::CloseTo(dock);
+                dock_writer.send(DockEvent::UnDocking);
+            }
+        }
+    }
+}
+
+pub fn wire_sensor_events(
+    mut collision_events: EventReader<CollisionEvent>,
+    player_query: Query<Entity, With<Player>>,
+    dock_query: Query<Entity, (With<Sensor>, With<Dock>)>,
+    mut player_data: ResMut<PlayerData>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+    for event in collision_events.iter() {
+        match event {
+            CollisionEvent::Started(entity1, entity2, ..) if *entity1 == player => {
+                for dock in dock_query.iter() {
+                    if dock == *entity2 {
+                        player_data.dock_state = DockState::CloseTo(dock);
+                    }
+                }
+            }
+            CollisionEvent::Stopped(entity1, entity2, ..) if *entity1 == player => {
+                for dock in dock_query.iter() {
+                    if dock == *entity2 {
+                        player_data.dock_state = DockState::TooFar;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{gltf::GltfPlugin, input::InputPlugin, scene::ScenePlugin};
+
+    fn card(trader: usize, dock: Entity) -> Card {
+        Card {
+            person_name: format!("trader {trader}"),
+            task: String::new(),
+            deal: Some(Deal { dock, trader }),
+        }
+    }
+
+    #[test]
+    fn menu_works_with_keys() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            ScenePlugin,
+            GltfPlugin::default(),
+            ActionsPlugin,
+            BoatPhysicsPlugin,
+            DockingPlugin,
+        ))
+        .add_asset::<Mesh>()
+        .add_state::<AssetState>()
+        .insert_resource(ManifestAssets::new(vec![]));
+
+        let dock = app.world.spawn(Dock { id: "test".into() }).id();
+        app.world
+            .entity_mut(dock)
+            .insert(DockCards(vec![card(0, dock), card(1, dock)]));
+        // the boat is not moving sideways, so it docks right away
+        app.world.resource_mut::<PlayerData>().dock_state = DockState::CloseTo(dock);
+        app.world
+            .resource_mut::<NextState<AssetState>>()
+            .set(AssetState::Loaded);
+        app.update();
+        app.update();
+
+        let menu = |app: &mut App| {
+            app.world
+                .query::<&DockMenu>()
+                .get_single(&app.world)
+                .ok()
+                .map(|menu| menu.selected)
+        };
+        assert_eq!(menu(&mut app), Some(1));
+
+        let press = |app: &mut App, key| {
+            app.world.resource_mut::<Input<KeyCode>>().press(key);
+            app.update();
+            app.world.resource_mut::<Input<KeyCode>>().release(key);
+            app.update();
+        };
+        press(&mut app, KeyCode::Left);
+        assert_eq!(menu(&mut app), Some(0));
+
+        let mut trades = app.world.resource::<Events<TradeEvent>>().get_reader();
+        press(&mut app, KeyCode::Return);
+        let events = app.world.resource::<Events<TradeEvent>>();
+        let deals: Vec<_> = trades
+            .iter(events)
+            .map(|TradeEvent(deal)| deal.trader)
+            .collect();
+        assert_eq!(deals, [0]);
+
+        press(&mut app, KeyCode::Escape);
+        assert_eq!(menu(&mut app), None);
+    }
 }
@@ -0,0 +1,207 @@
+use crate::custom_assets::JsonAssetPlugin;
+use bevy::{ecs::system::SystemParam, prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Fills `ActionState` from the keyboard, gamepads and touch, with the bindings in `InputBindingsHandle`.
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<InputBindings>::new(&["bindings.json"]))
+            .init_resource::<ActionState>()
+            .add_systems(PreUpdate, update_actions.after(bevy::input::InputSystem));
+    }
+}
+
+/// something the player wants to do, independent of the device used to do it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Throttle,
+    Reverse,
+    SteerLeft,
+    SteerRight,
+    SheetIn,
+    EaseOut,
+    Interact,
+    Menu,
+    /// moves the selection in menus
+    NavigateLeft,
+    NavigateRight,
+    OrbitLeft,
+    OrbitRight,
+    OrbitUp,
+    OrbitDown,
+    ZoomIn,
+    ZoomOut,
+    ToggleCamera,
+}
+
+/// a physical input that can trigger an action
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    GamepadButton(GamepadButtonType),
+    /// only the part of the axis pointing in the given direction counts
+    GamepadAxis {
+        axis: GamepadAxisType,
+        positive: bool,
+    },
+    /// a region of the screen, in fractions of the window size from the top left corner
+    Touch {
+        min: Vec2,
+        max: Vec2,
+    },
+}
+
+/// which bindings trigger each action, from the `bindings` entry in the asset manifest
+#[derive(Serialize, Deserialize, Clone, bevy::reflect::TypeUuid, bevy::reflect::TypePath)]
+#[uuid = "7c1d2f4e-3a8b-4e2d-9f61-5b0c8d3e7a12"]
+pub struct InputBindings {
+    pub actions: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    /// the shipped bindings file, built into the game so the controls work before it is loaded
+    fn default() -> Self {
+        serde_json::from_str(include_str!("../assets/input.bindings.json"))
+            .expect("the shipped input bindings should be valid")
+    }
+}
+
+/// handle to the bindings file, the default bindings are used until it is loaded
+#[derive(Resource)]
+pub struct InputBindingsHandle(pub Handle<InputBindings>);
+
+/// how much each action is held this frame, from 0 to 1
+#[derive(Resource, Default)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    just_pressed: HashSet<Action>,
+}
+
+impl ActionState {
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        0.5 < self.value(action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// the difference between two opposing actions, from -1 to 1
+    pub fn axis(&self, positive: Action, negative: Action) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+}
+
+#[derive(SystemParam)]
+pub struct GamepadInput<'w> {
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    button_axes: Res<'w, Axis<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+#[derive(SystemParam)]
+pub struct TouchInput<'w, 's> {
+    touches: Res<'w, Touches>,
+    window: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+}
+
+pub fn update_actions(
+    mut actions: ResMut<ActionState>,
+    handle: Option<Res<InputBindingsHandle>>,
+    assets_bindings: Res<Assets<InputBindings>>,
+    fallback: Local<InputBindings>,
+    keyboard: Res<Input<KeyCode>>,
+    gamepad: GamepadInput,
+    touch: TouchInput,
+) {
+    let bindings = handle
+        .and_then(|h| assets_bindings.get(&h.0))
+        .unwrap_or(&fallback);
+    let window_size = touch
+        .window
+        .get_single()
+        .map(|w| Vec2::new(w.width(), w.height()))
+        .unwrap_or(Vec2::ONE);
+
+    let binding_value = |binding: &Binding| -> f32 {
+        match binding {
+            Binding::Key(key) => keyboard.pressed(*key) as i8 as f32,
+            Binding::GamepadButton(button_type) => gamepad
+                .gamepads
+                .iter()
+                .map(|id| {
+                    let button = GamepadButton::new(id, *button_type);
+                    // triggers are analog, the rest are just pressed or not
+                    gamepad
+                        .button_axes
+                        .get(button)
+                        .unwrap_or(gamepad.buttons.pressed(button) as i8 as f32)
+                })
+                .fold(0., f32::max),
+            Binding::GamepadAxis { axis, positive } => gamepad
+                .gamepads
+                .iter()
+                .filter_map(|id| gamepad.axes.get(GamepadAxis::new(id, *axis)))
+                .map(|v| if *positive { v } else { -v })
+                .fold(0., f32::max),
+            Binding::Touch { min, max } => touch
+                .touches
+                .iter()
+                .map(|finger| finger.position() / window_size)
+                .any(|p| min.x <= p.x && p.x <= max.x && min.y <= p.y && p.y <= max.y)
+                as i8 as f32,
+        }
+    };
+
+    let previous = std::mem::take(&mut actions.values);
+    actions.just_pressed.clear();
+    for (action, action_bindings) in bindings.actions.iter() {
+        let value = action_bindings
+            .iter()
+            .map(binding_value)
+            .fold(0., f32::max)
+            .clamp(0., 1.);
+        if 0.5 < value && previous.get(action).copied().unwrap_or(0.) <= 0.5 {
+            actions.just_pressed.insert(*action);
+        }
+        actions.values.insert(*action, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::InputPlugin;
+
+    #[test]
+    fn keys_press_actions() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            ActionsPlugin,
+        ));
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+        let actions = app.world.resource::<ActionState>();
+        assert!(actions.pressed(Action::Throttle));
+        assert!(actions.just_pressed(Action::Throttle));
+        assert!(!actions.pressed(Action::Reverse));
+
+        app.update();
+        let actions = app.world.resource::<ActionState>();
+        assert!(actions.pressed(Action::Throttle));
+        assert!(!actions.just_pressed(Action::Throttle));
+    }
+}
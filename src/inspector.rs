@@ -0,0 +1,184 @@
+use bevy::{
+    prelude::*,
+    reflect::{DynamicEnum, DynamicVariant, ReflectMut, TypeInfo, VariantInfo},
+};
+use bevy_egui::egui::{emath::Numeric, ComboBox, DragValue, Slider, Ui};
+
+/// how a number is shown in the inspector, bevy_reflect has no field attributes to keep this on the field
+pub struct FieldHint {
+    /// field names from the top, like `wind.strength`, without array indices
+    pub path: &'static str,
+    pub min: f64,
+    pub max: f64,
+    pub logarithmic: bool,
+}
+
+impl FieldHint {
+    pub const fn range(path: &'static str, min: f64, max: f64) -> Self {
+        Self {
+            path,
+            min,
+            max,
+            logarithmic: false,
+        }
+    }
+
+    pub const fn logarithmic(path: &'static str, min: f64, max: f64) -> Self {
+        Self {
+            path,
+            min,
+            max,
+            logarithmic: true,
+        }
+    }
+}
+
+/// draws a widget for every field in `value`, and returns whether any of them changed
+///
+/// Numbers with a hint get a slider and the rest a drag value, nested structs, arrays and lists are
+/// collapsible, colors get a color picker and enums with only unit variants a dropdown.
+pub fn inspect(ui: &mut Ui, value: &mut dyn Reflect, hints: &[FieldHint]) -> bool {
+    inspect_field(ui, value, "", "", hints)
+}
+
+fn inspect_field(
+    ui: &mut Ui,
+    value: &mut dyn Reflect,
+    label: &str,
+    path: &str,
+    hints: &[FieldHint],
+) -> bool {
+    let hint = hints.iter().find(|hint| hint.path == path);
+    if let Some(v) = value.downcast_mut::<f32>() {
+        return number(ui, v, label, hint);
+    }
+    if let Some(v) = value.downcast_mut::<f64>() {
+        return number(ui, v, label, hint);
+    }
+    if let Some(v) = value.downcast_mut::<u32>() {
+        return number(ui, v, label, hint);
+    }
+    if let Some(v) = value.downcast_mut::<i32>() {
+        return number(ui, v, label, hint);
+    }
+    if let Some(v) = value.downcast_mut::<usize>() {
+        return number(ui, v, label, hint);
+    }
+    if let Some(v) = value.downcast_mut::<bool>() {
+        return ui.checkbox(v, label).changed();
+    }
+    if let Some(color) = value.downcast_mut::<Color>() {
+        return ui
+            .horizontal(|ui| {
+                let mut buf = [color.r(), color.g(), color.b()];
+                let changed = ui.color_edit_button_rgb(&mut buf).changed();
+                if changed {
+                    *color = buf.into();
+                }
+                ui.label(label);
+                changed
+            })
+            .inner;
+    }
+    if let Some(v) = value.downcast_mut::<Vec3>() {
+        return ui
+            .horizontal(|ui| {
+                let changed = ui.add(DragValue::new(&mut v.x).speed(0.01)).changed()
+                    | ui.add(DragValue::new(&mut v.y).speed(0.01)).changed()
+                    | ui.add(DragValue::new(&mut v.z).speed(0.01)).changed();
+                ui.label(label);
+                changed
+            })
+            .inner;
+    }
+
+    let is_group = matches!(
+        value.reflect_mut(),
+        ReflectMut::Struct(_) | ReflectMut::Array(_) | ReflectMut::List(_)
+    );
+    let mut changed = false;
+    let mut fields = |ui: &mut Ui| match value.reflect_mut() {
+        ReflectMut::Struct(s) => {
+            for i in 0..s.field_len() {
+                let name = s.name_at(i).unwrap_or_default().to_owned();
+                let path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{path}.{name}")
+                };
+                if let Some(field) = s.field_at_mut(i) {
+                    changed |= inspect_field(ui, field, &name.replace('_', " "), &path, hints);
+                }
+            }
+        }
+        ReflectMut::Array(a) => {
+            for i in 0..a.len() {
+                if let Some(item) = a.get_mut(i) {
+                    changed |= inspect_field(ui, item, &format!("{label} {}", i + 1), path, hints);
+                }
+            }
+        }
+        ReflectMut::List(l) => {
+            for i in 0..l.len() {
+                if let Some(item) = l.get_mut(i) {
+                    changed |= inspect_field(ui, item, &format!("{label} {}", i + 1), path, hints);
+                }
+            }
+        }
+        ReflectMut::Enum(e) => {
+            let Some(TypeInfo::Enum(info)) = e.get_represented_type_info() else {
+                return;
+            };
+            let mut selected = e.variant_name().to_owned();
+            ComboBox::from_label(label)
+                .selected_text(&selected)
+                .show_ui(ui, |ui| {
+                    for variant in info.iter() {
+                        if let VariantInfo::Unit(_) = variant {
+                            ui.selectable_value(
+                                &mut selected,
+                                variant.name().into(),
+                                variant.name(),
+                            );
+                        }
+                    }
+                });
+            if selected != e.variant_name() {
+                e.apply(&DynamicEnum::new(selected, DynamicVariant::Unit));
+                changed = true;
+            }
+        }
+        _ => {
+            ui.label(format!("{label} can not be edited here"));
+        }
+    };
+
+    if is_group && !label.is_empty() {
+        ui.collapsing(label, fields);
+    } else {
+        fields(ui);
+    }
+    changed
+}
+
+fn number<N: Numeric>(ui: &mut Ui, value: &mut N, label: &str, hint: Option<&FieldHint>) -> bool {
+    match hint {
+        Some(hint) => ui
+            .add(
+                Slider::new(value, N::from_f64(hint.min)..=N::from_f64(hint.max))
+                    .logarithmic(hint.logarithmic)
+                    .text(label),
+            )
+            .changed(),
+        None => {
+            ui.horizontal(|ui| {
+                let changed = ui
+                    .add(DragValue::new(value).speed(if N::INTEGRAL { 1. } else { 0.01 }))
+                    .changed();
+                ui.label(label);
+                changed
+            })
+            .inner
+        }
+    }
+}
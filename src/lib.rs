@@ -0,0 +1,31 @@
+//! A sailing game, split into plugins so the boat simulation can be used in other Bevy apps.
+//!
+//! `main.rs` puts them together with the scenes and lights of the game.
+
+pub mod camera;
+pub mod cli;
+pub mod colliders;
+pub mod config;
+pub mod custom_assets;
+pub mod debug_ui;
+pub mod dock;
+pub mod input;
+pub mod inspector;
+pub mod loading;
+pub mod migration;
+pub mod physics;
+pub mod presets;
+pub mod sailing;
+pub mod storage;
+pub mod trade;
+pub mod utils;
+pub mod water;
+
+pub use camera::CameraPlugin;
+pub use config::ConfigPlugin;
+pub use debug_ui::DebugUiPlugin;
+pub use dock::DockingPlugin;
+pub use input::ActionsPlugin;
+pub use loading::AssetLoadingPlugin;
+pub use physics::BoatPhysicsPlugin;
+pub use trade::TradePlugin;
@@ -0,0 +1,603 @@
+use crate::{cli::Args, custom_assets::JsonAssetPlugin, input::*};
+use bevy::{
+    asset::{Asset, AssetIoError, HandleId, LoadState},
+    ecs::query::Has,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const MANIFEST_NAME: &str = "assets.manifest.json";
+
+/// Loads everything in `MANIFEST_NAME`, and moves to `AssetState::Loaded` once the assets it waits for are in.
+///
+/// Shows a loading screen meanwhile, and a screen with the reasons and a retry button if they fail.
+pub struct AssetLoadingPlugin;
+
+impl Plugin for AssetLoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<AssetManifest>::new(&["manifest.json"]))
+            .add_state::<AssetState>()
+            .init_resource::<LoadFailures>()
+            .init_resource::<ActionState>()
+            .init_resource::<Args>()
+            .add_systems(PostStartup, start_loading_manifest)
+            .add_systems(
+                Update,
+                (
+                    load_manifest_entries,
+                    check_load_state.after(load_manifest_entries),
+                    update_loading_screen,
+                )
+                    .run_if(in_state(AssetState::Loading)),
+            )
+            .add_systems(OnEnter(AssetState::Loading), spawn_loading_screen)
+            .add_systems(OnExit(AssetState::Loading), despawn_loading_screen)
+            .add_systems(OnEnter(AssetState::Failed), spawn_failure_screen)
+            .add_systems(OnExit(AssetState::Failed), despawn_failure_screen)
+            .add_systems(Update, retry_loading.run_if(in_state(AssetState::Failed)));
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum AssetState {
+    #[default]
+    Loading,
+    Loaded,
+    Failed,
+}
+
+/// every asset the game loads at startup, loaded from `MANIFEST_NAME`
+#[derive(Serialize, Deserialize, Clone, bevy::reflect::TypeUuid, bevy::reflect::TypePath)]
+#[uuid = "d4a6e0b1-5c3f-4f8e-a2d7-91b6c4e8f305"]
+pub struct AssetManifest {
+    pub assets: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestEntry {
+    /// what the code asks for, so the file can be moved without changing the code
+    pub name: String,
+    /// relative to the assets folder, with a label like `#Scene0` if only part of the file is used
+    pub path: String,
+    /// the game can not run without it, if it is not vital the game goes on without it
+    #[serde(default)]
+    pub vital: bool,
+}
+
+impl ManifestEntry {
+    /// whether the game waits for it before starting, which is the vital assets and
+    /// the config and preset that are read when it starts, everything else shows up when it is loaded
+    pub fn awaited(&self, args: &Args) -> bool {
+        self.vital
+            || self.name == "config"
+            || args
+                .preset
+                .as_ref()
+                .is_some_and(|preset| self.name == format!("preset/{preset}"))
+    }
+}
+
+#[derive(Resource)]
+pub struct ManifestHandle(pub Handle<AssetManifest>);
+
+/// handles to everything in the manifest, inserted once the manifest itself is loaded
+#[derive(Resource)]
+pub struct ManifestAssets {
+    entries: Vec<(ManifestEntry, HandleUntyped)>,
+}
+
+impl ManifestAssets {
+    /// assets that are already loading, for apps that do not load them from a manifest file
+    pub fn new(entries: Vec<(ManifestEntry, HandleUntyped)>) -> Self {
+        Self { entries }
+    }
+
+    /// the handle to the asset with this name in the manifest
+    pub fn get<T: Asset>(&self, name: &str) -> Option<Handle<T>> {
+        let handle = self
+            .entries
+            .iter()
+            .find(|(entry, _)| entry.name == name)
+            .map(|(_, handle)| handle.clone().typed());
+        if handle.is_none() {
+            error!("no asset named '{name}' in the manifest");
+        }
+        handle
+    }
+
+    /// the names of all the assets in the manifest
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(entry, _)| entry.name.as_str())
+    }
+
+    pub fn load_states<'a>(
+        &'a self,
+        asset_server: &'a AssetServer,
+    ) -> impl Iterator<Item = (&'a ManifestEntry, LoadState)> + 'a {
+        self.entries
+            .iter()
+            .map(|(entry, handle)| (entry, asset_server.get_load_state(handle.id())))
+    }
+}
+
+pub fn start_loading_manifest(mut cmd: Commands, asset_server: Res<AssetServer>) {
+    cmd.insert_resource(ManifestHandle(asset_server.load(MANIFEST_NAME)));
+}
+
+/// starts loading everything in the manifest, once it is loaded
+pub fn load_manifest_entries(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<AssetManifest>>,
+    loaded: Option<Res<ManifestAssets>>,
+    args: Res<Args>,
+) {
+    if loaded.is_some() {
+        return;
+    }
+    let Some(manifest) = manifests.get(&manifest.0) else {
+        return;
+    };
+    cmd.insert_resource(ManifestAssets {
+        entries: manifest
+            .assets
+            .iter()
+            .map(|entry| {
+                let mut entry = entry.clone();
+                if let (Some(config), "config") = (&args.config, entry.name.as_str()) {
+                    entry.path = config.clone();
+                }
+                let handle = asset_server.load_untyped(entry.path.as_str());
+                (entry, handle)
+            })
+            .collect(),
+    });
+}
+
+/// a vital asset that failed to load, and a guess at why
+pub struct LoadFailure {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Resource, Default)]
+pub struct LoadFailures {
+    pub failures: Vec<LoadFailure>,
+    /// the assets loaded again by the last retry, the game goes back to loading once none of them look failed
+    retrying: Vec<HandleId>,
+}
+
+/// the asset server only logs the real error, so this checks the file to give the player a hint
+fn failure_reason(asset_server: &AssetServer, path: &str) -> String {
+    let file = path.split('#').next().unwrap_or(path);
+    match asset_server.asset_io().get_metadata(Path::new(file)) {
+        Err(AssetIoError::NotFound(_)) => "the file does not exist".into(),
+        Err(e) if !cfg!(target_arch = "wasm32") => e.to_string(),
+        _ => "the file could not be read, it may be broken or still a Git LFS pointer".into(),
+    }
+}
+
+pub fn check_load_state(
+    asset_server: Res<AssetServer>,
+    manifest: Res<ManifestHandle>,
+    assets: Option<Res<ManifestAssets>>,
+    args: Res<Args>,
+    mut failures: ResMut<LoadFailures>,
+    mut next_state: ResMut<NextState<AssetState>>,
+) {
+    use LoadState::*;
+
+    let Some(assets) = assets else {
+        if asset_server.get_load_state(manifest.0.id()) == Failed {
+            error!("the asset manifest '{MANIFEST_NAME}' failed to load");
+            failures.failures = vec![LoadFailure {
+                path: MANIFEST_NAME.into(),
+                reason: failure_reason(&asset_server, MANIFEST_NAME),
+            }];
+            next_state.set(AssetState::Failed);
+        }
+        return;
+    };
+    if !assets
+        .load_states(&asset_server)
+        .filter(|(entry, _)| entry.awaited(&args))
+        .all(|(_, state)| matches!(state, Loaded | Failed))
+    {
+        return;
+    }
+
+    failures.failures.clear();
+    for (entry, _) in assets
+        .load_states(&asset_server)
+        .filter(|(_, state)| *state == Failed)
+    {
+        if entry.vital {
+            error!("vital asset '{}' failed to load", entry.path);
+            failures.failures.push(LoadFailure {
+                path: entry.path.clone(),
+                reason: failure_reason(&asset_server, &entry.path),
+            });
+        } else {
+            warn!("asset '{}' failed to load", entry.path);
+        }
+    }
+    next_state.set(if failures.failures.is_empty() {
+        AssetState::Loaded
+    } else {
+        AssetState::Failed
+    });
+}
+
+/// the cameras from the glTF files may not be loaded, so the loading and failure screens bring their own
+fn screen_camera() -> Camera2dBundle {
+    Camera2dBundle {
+        camera: bevy::render::camera::Camera {
+            order: 1,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// the loaded font from the manifest, or the default font until it is loaded
+fn screen_font(asset_server: &AssetServer, assets: Option<&ManifestAssets>) -> Handle<Font> {
+    assets
+        .and_then(|assets| assets.get::<Font>("font"))
+        .filter(|font| asset_server.get_load_state(font) == LoadState::Loaded)
+        .unwrap_or_default()
+}
+
+/// everything shown while in `AssetState::Loading`
+#[derive(Component)]
+pub struct LoadingScreen;
+
+/// the filled part of the progress bar
+#[derive(Component)]
+pub struct ProgressBar;
+
+/// lists the assets that are still loading
+#[derive(Component)]
+pub struct PendingAssets;
+
+/// text on the loading screen, which switches to the game font once it is loaded
+#[derive(Component)]
+pub struct LoadingText;
+
+pub fn spawn_loading_screen(mut cmd: Commands) {
+    let text_style = |font_size| TextStyle {
+        font_size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    cmd.spawn((LoadingScreen, screen_camera()));
+    cmd.spawn((
+        LoadingScreen,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgb(0.05, 0.05, 0.1).into(),
+            ..default()
+        },
+    ))
+    .with_children(|cmd| {
+        cmd.spawn((
+            LoadingText,
+            TextBundle::from_section("loading", text_style(32.)),
+        ));
+        cmd.spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(50.),
+                height: Val::Px(20.),
+                padding: UiRect::all(Val::Px(3.)),
+                ..default()
+            },
+            background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+            ..default()
+        })
+        .with_children(|cmd| {
+            cmd.spawn((
+                ProgressBar,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: Color::rgb(1., 0.85, 0.63).into(),
+                    ..default()
+                },
+            ));
+        });
+        cmd.spawn((
+            LoadingText,
+            PendingAssets,
+            TextBundle::from_section("", text_style(16.)),
+        ));
+    });
+}
+
+pub fn update_loading_screen(
+    asset_server: Res<AssetServer>,
+    assets: Option<Res<ManifestAssets>>,
+    args: Res<Args>,
+    mut bar: Query<&mut Style, With<ProgressBar>>,
+    mut texts: Query<(&mut Text, Has<PendingAssets>), With<LoadingText>>,
+) {
+    // before the manifest is loaded, it is the only thing pending
+    let (done, total, pending_paths) = match assets.as_deref() {
+        None => (0, 1, vec![MANIFEST_NAME]),
+        Some(assets) => {
+            let states: Vec<_> = assets
+                .load_states(&asset_server)
+                .filter(|(entry, _)| entry.awaited(&args))
+                .collect();
+            let pending_paths: Vec<_> = states
+                .iter()
+                .filter(|(_, state)| !matches!(state, LoadState::Loaded | LoadState::Failed))
+                .map(|(entry, _)| entry.path.as_str())
+                .collect();
+            (
+                states.len() - pending_paths.len(),
+                states.len().max(1),
+                pending_paths,
+            )
+        }
+    };
+
+    for mut style in bar.iter_mut() {
+        style.width = Val::Percent(100. * done as f32 / total as f32);
+    }
+
+    let font = screen_font(&asset_server, assets.as_deref());
+    let pending_text = format!("waiting for {}", pending_paths.join(", "));
+    for (mut text, is_pending) in texts.iter_mut() {
+        if is_pending && text.sections[0].value != pending_text {
+            text.sections[0].value = pending_text.clone();
+        }
+        if text.sections[0].style.font != font {
+            text.sections[0].style.font = font.clone();
+        }
+    }
+}
+
+pub fn despawn_loading_screen(mut cmd: Commands, screen: Query<Entity, With<LoadingScreen>>) {
+    for entity in screen.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+/// everything shown while in `AssetState::Failed`
+#[derive(Component)]
+pub struct FailureScreen;
+
+#[derive(Component)]
+pub struct RetryButton;
+
+pub fn spawn_failure_screen(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    failures: Res<LoadFailures>,
+    assets: Option<Res<ManifestAssets>>,
+) {
+    // the font may be one of the assets that failed, then the default font is used
+    let font = screen_font(&asset_server, assets.as_deref());
+    let text_style = |font_size| TextStyle {
+        font: font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    cmd.spawn((FailureScreen, screen_camera()));
+    cmd.spawn((
+        FailureScreen,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgb(0.05, 0.05, 0.1).into(),
+            ..default()
+        },
+    ))
+    .with_children(|cmd| {
+        cmd.spawn(TextBundle::from_section(
+            "some files the game needs could not be loaded",
+            text_style(32.),
+        ));
+        for failure in failures.failures.iter() {
+            cmd.spawn(TextBundle::from_section(
+                format!("{}: {}", failure.path, failure.reason),
+                text_style(18.),
+            ));
+        }
+        cmd.spawn((
+            RetryButton,
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(20.), Val::Px(10.)),
+                    margin: UiRect::all(Val::Px(20.)),
+                    ..default()
+                },
+                background_color: Color::rgb(1., 0.85, 0.63).into(),
+                ..default()
+            },
+        ))
+        .with_children(|cmd| {
+            cmd.spawn(TextBundle::from_section(
+                "retry",
+                TextStyle {
+                    color: Color::BLACK,
+                    ..text_style(24.)
+                },
+            ));
+        });
+    });
+}
+
+pub fn despawn_failure_screen(mut cmd: Commands, screen: Query<Entity, With<FailureScreen>>) {
+    for entity in screen.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+/// loads the failed assets again when the retry button or the interact action is pressed
+///
+/// The asset server marks retried assets as loading from another thread, so they can still look
+/// failed for a while. The failure screen stays up until none of them do, so an asset that fails
+/// again right away keeps it up and can be retried once more.
+pub fn retry_loading(
+    actions: Res<ActionState>,
+    asset_server: Res<AssetServer>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<RetryButton>)>,
+    mut failures: ResMut<LoadFailures>,
+    mut next_state: ResMut<NextState<AssetState>>,
+) {
+    if actions.just_pressed(Action::Interact) || buttons.iter().any(|i| *i == Interaction::Pressed)
+    {
+        for failure in failures.failures.iter() {
+            info!("retrying '{}'", failure.path);
+            asset_server.reload_asset(failure.path.as_str());
+        }
+        failures.retrying = failures
+            .failures
+            .iter()
+            .map(|failure| failure.path.as_str().into())
+            .collect();
+    }
+    if failures.retrying.is_empty()
+        || failures
+            .retrying
+            .iter()
+            .any(|id| asset_server.get_load_state(*id) == LoadState::Failed)
+    {
+        return;
+    }
+    failures.retrying.clear();
+    failures.failures.clear();
+    next_state.set(AssetState::Loading);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::InputPlugin;
+
+    #[test]
+    fn shows_loading_screen() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), AssetLoadingPlugin));
+        app.update();
+
+        assert_eq!(
+            *app.world.resource::<State<AssetState>>().get(),
+            AssetState::Loading
+        );
+        assert!(app.world.contains_resource::<ManifestHandle>());
+        assert!(app
+            .world
+            .query_filtered::<(), With<LoadingScreen>>()
+            .iter(&app.world)
+            .next()
+            .is_some());
+    }
+
+    /// the failure screen for `path`, after the asset server has tried to load it
+    fn failed_app(path: &str) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            ActionsPlugin,
+            JsonAssetPlugin::<AssetManifest>::new(&["manifest.json"]),
+        ))
+        .add_state::<AssetState>()
+        .init_resource::<LoadFailures>()
+        .add_systems(Update, retry_loading.run_if(in_state(AssetState::Failed)));
+        let handle = app.world.resource::<AssetServer>().load_untyped(path);
+        while matches!(
+            app.world.resource::<AssetServer>().get_load_state(&handle),
+            LoadState::NotLoaded | LoadState::Loading
+        ) {
+            app.update();
+        }
+        // the manifest keeps the handles alive in the game
+        app.insert_resource(ManifestAssets::new(vec![(
+            ManifestEntry {
+                name: "retried".into(),
+                path: path.into(),
+                vital: true,
+            },
+            handle,
+        )]))
+        .insert_resource(LoadFailures {
+            failures: vec![LoadFailure {
+                path: path.into(),
+                reason: String::new(),
+            }],
+            ..default()
+        });
+        app.world
+            .resource_mut::<NextState<AssetState>>()
+            .set(AssetState::Failed);
+        app.update();
+        app
+    }
+
+    fn press_retry(app: &mut App) {
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Return);
+        app.update();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::Return);
+    }
+
+    fn state(app: &App) -> AssetState {
+        *app.world.resource::<State<AssetState>>().get()
+    }
+
+    #[test]
+    fn retry_waits_for_the_reload() {
+        let mut app = failed_app(MANIFEST_NAME);
+        assert_eq!(state(&app), AssetState::Failed);
+
+        press_retry(&mut app);
+        for _ in 0..100 {
+            app.update();
+            if state(&app) == AssetState::Loading {
+                return;
+            }
+        }
+        panic!("the retried file never left the failure screen");
+    }
+
+    #[test]
+    fn stays_failed_when_retry_fails() {
+        let mut app = failed_app("missing.manifest.json");
+        assert_eq!(state(&app), AssetState::Failed);
+
+        press_retry(&mut app);
+        for _ in 0..20 {
+            app.update();
+            assert_eq!(state(&app), AssetState::Failed);
+        }
+        assert_eq!(app.world.resource::<LoadFailures>().failures.len(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::{ConfigValues, CONFIG_VERSION};
+use bevy::prelude::*;
+use serde_json::{Map, Value};
+
+/// each function takes a config from the version at its index to the next one
+const MIGRATIONS: [fn(&mut Map<String, Value>); CONFIG_VERSION as usize] = [split_drag];
+
+/// brings a config file from an older version up to `CONFIG_VERSION`, and warns about keys that do not match
+pub fn migrate_config(mut value: Value) -> Value {
+    let Some(config) = value.as_object_mut() else {
+        return value;
+    };
+    let mut version = match config.get("version").and_then(Value::as_u64) {
+        Some(version) => version as u32,
+        // files from before the version field had a single drag coefficient
+        None if config.contains_key("drag_c") => 0,
+        None => 1,
+    };
+    if CONFIG_VERSION < version {
+        warn!(
+            "the config is version {version}, but the newest this game knows is {CONFIG_VERSION}"
+        );
+    }
+    while version < CONFIG_VERSION {
+        MIGRATIONS[version as usize](config);
+        version += 1;
+        info!("migrated the config to version {version}");
+    }
+    config.insert("version".into(), version.into());
+
+    let defaults = serde_json::to_value(ConfigValues::default()).unwrap_or_default();
+    warn_mismatched_keys(&value, &defaults, "");
+    value
+}
+
+/// version 0 had one drag coefficient for every direction, as the fraction of speed lost each frame at 60 fps
+fn split_drag(config: &mut Map<String, Value>) {
+    let per_second = |c: f64| -(1. - c.clamp(0., 0.99)).ln() * 60.;
+    if let Some(drag) = config.remove("drag_c").and_then(|v| v.as_f64()) {
+        // the linear drag was taken off twice each frame, once against the velocity and once along it
+        // the hull slips much less sideways and up and down, with the same ratios as the defaults
+        let forward = 2. * per_second(drag);
+        config.insert("drag_forward_c".into(), forward.into());
+        config.insert("drag_lateral_c".into(), (forward * 15.).into());
+        config.insert("drag_vertical_c".into(), (forward * 5.).into());
+    }
+    if let Some(drag) = config.get("drag_ang_c").and_then(Value::as_f64) {
+        config.insert("drag_ang_c".into(), per_second(drag).into());
+    }
+}
+
+/// warns about keys in `file` that are not in `defaults`, and the other way around, in nested objects too
+///
+/// Every element of an array is checked against the first element of the default array.
+fn warn_mismatched_keys(file: &Value, defaults: &Value, path: &str) {
+    if let (Some(file), Some(default)) = (file.as_array(), defaults.as_array()) {
+        let Some(default) = default.first() else {
+            return;
+        };
+        let path = path.trim_end_matches('.');
+        for (i, value) in file.iter().enumerate() {
+            warn_mismatched_keys(value, default, &format!("{path}[{i}]."));
+        }
+        return;
+    }
+    let (Some(file), Some(defaults)) = (file.as_object(), defaults.as_object()) else {
+        return;
+    };
+    // enums like `Color` are objects with the variant as the only key, and any variant is fine
+    if defaults.len() == 1
+        && defaults
+            .keys()
+            .all(|k| k.starts_with(|c: char| c.is_uppercase()))
+    {
+        return;
+    }
+    for key in file.keys().filter(|k| !defaults.contains_key(*k)) {
+        warn!("unknown config key '{path}{key}' is ignored");
+    }
+    for (key, default) in defaults {
+        match file.get(key) {
+            None => warn!("config key '{path}{key}' is missing, the default is used"),
+            Some(value) => warn_mismatched_keys(value, default, &format!("{path}{key}.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_single_drag() {
+        let old = json!({
+            "drag_c": 0.1,
+            "drag_ang_c": 0.1,
+            "avg_boat_height": 1.5,
+            "floating_c": 2.0,
+        });
+        let migrated = migrate_config(old);
+        let config: ConfigValues = serde_json::from_value(migrated.clone()).unwrap();
+
+        // the same speed is kept each frame at 60 fps, 0.9 for each time the drag was applied
+        let kept = |c: f32| (-c / 60.).exp();
+        assert!((kept(config.drag_forward_c) - 0.9 * 0.9).abs() < 1e-5);
+        assert!((kept(config.drag_ang_c) - 0.9).abs() < 1e-5);
+        assert!((config.drag_lateral_c - config.drag_forward_c * 15.).abs() < 1e-3);
+        assert!((config.drag_vertical_c - config.drag_forward_c * 5.).abs() < 1e-3);
+        assert_eq!(config.avg_boat_height, 1.5);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(migrated.get("drag_c").is_none());
+    }
+
+    #[test]
+    fn leaves_current_version_alone() {
+        let current = serde_json::to_value(ConfigValues::default()).unwrap();
+        assert_eq!(migrate_config(current.clone()), current);
+    }
+}
@@ -0,0 +1,520 @@
+use crate::{colliders::*, config::*, input::*, loading::*, sailing::*, utils::*, water::*};
+use bevy::{app::RunFixedUpdateLoop, prelude::*, time::fixed_timestep::run_fixed_update_schedule};
+use bevy_rapier3d::prelude::*;
+
+/// length of a physics step in seconds, independent of the frame rate
+pub const PHYSICS_DT: f32 = 1. / 60.;
+
+/// Floats, drags and pushes boats around with Rapier, in fixed steps of `PHYSICS_DT`.
+///
+/// Spawns the player's boat, and the simulation starts once the vital assets are loaded.
+/// Bodies with a `PhysicsPose` are drawn between the last two steps, so they move smoothly at any
+/// frame rate.
+/// The colliders come from the manifest's `bboxes` entry, so this needs Bevy's `GltfPlugin`.
+/// The forces use the values in `Config`.
+pub struct BoatPhysicsPlugin;
+
+impl Plugin for BoatPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            // the physics steps are added to `FixedUpdate` below, together with the environment forces
+            RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false),
+            GltfColliderPlugin,
+        ))
+        .insert_resource(FixedTime::new_from_secs(PHYSICS_DT))
+        .insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed {
+                dt: PHYSICS_DT,
+                substeps: 1,
+            },
+            ..default()
+        })
+        .init_resource::<Config>()
+        .add_event::<ConfigEvent>()
+        .init_resource::<SimulationTime>()
+        .init_resource::<WaterSurface>()
+        .init_resource::<Wind>()
+        .init_resource::<ActionState>()
+        .configure_sets(
+            FixedUpdate,
+            (
+                PhysicsSet::SyncBackend,
+                PhysicsSet::SyncBackendFlush,
+                PhysicsSet::StepSimulation,
+                PhysicsSet::Writeback,
+            )
+                .chain(),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::SyncBackend)
+                    .in_set(PhysicsSet::SyncBackend),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::SyncBackendFlush)
+                    .in_set(PhysicsSet::SyncBackendFlush),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::StepSimulation)
+                    .in_set(PhysicsSet::StepSimulation),
+                RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsSet::Writeback)
+                    .in_set(PhysicsSet::Writeback),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                (
+                    restore_physics_poses,
+                    add_env_forces.after(restore_physics_poses),
+                    apply_propulsion.after(add_env_forces),
+                    apply_sail_forces.after(add_env_forces),
+                )
+                    .before(PhysicsSet::SyncBackend),
+                (advance_simulation_time, record_physics_poses).after(PhysicsSet::Writeback),
+            )
+                .run_if(in_state(AssetState::Loaded)),
+        )
+        .add_systems(
+            RunFixedUpdateLoop,
+            interpolate_physics_poses.after(run_fixed_update_schedule),
+        )
+        .add_systems(Startup, spawn_player_boat)
+        .add_systems(
+            OnEnter(AssetState::Loaded),
+            spawn_colliders.after(ConfigSet),
+        )
+        .add_systems(Update, setup_hulls)
+        .add_systems(
+            Update,
+            (player_input_system, update_water, update_wind).run_if(in_state(AssetState::Loaded)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct Player;
+
+#[derive(Component)]
+pub struct MovingObject;
+
+/// samples the water at points on the hull, and lifts the hull at each point that is under water
+#[derive(Component, Default)]
+pub struct Buoyancy {
+    /// points in local space, usually spread across the bottom of the hull
+    pub points: Vec<Vec3>,
+}
+
+/// engine and rudder of a boat, the forces are added on top of the environment forces
+#[derive(Component)]
+pub struct Propulsion {
+    /// from -1, full reverse, to 1, full ahead
+    pub throttle: f32,
+    /// from -1, hard to starboard, to 1, hard to port
+    pub rudder: f32,
+    /// force from the engine at full throttle ahead
+    pub thrust: f32,
+    /// how much of the thrust is available in reverse
+    pub reverse_ratio: f32,
+    /// turning torque for each unit of speed through the water, at full rudder
+    pub rudder_torque: f32,
+}
+
+impl Default for Propulsion {
+    fn default() -> Self {
+        Self {
+            throttle: 0.,
+            rudder: 0.,
+            // top speed is thrust / (mass * drag_forward_c), 5 m/s with the default config
+            thrust: 2_500.,
+            reverse_ratio: 0.66,
+            rudder_torque: 3_000.,
+        }
+    }
+}
+
+/// the boat the player sails, it gets its collider from the node named `boat` in the colliders file
+#[derive(Bundle)]
+pub struct PlayerBoatBundle {
+    pub player: Player,
+    pub moving_object: MovingObject,
+    pub rigid_body: RigidBody,
+    pub velocity: Velocity,
+    pub active_events: ActiveEvents,
+    pub external_force: ExternalForce,
+    pub mass: ReadMassProperties,
+    pub buoyancy: Buoyancy,
+    pub propulsion: Propulsion,
+    pub sail: Sail,
+    pub collider_name: ColliderName,
+    pub transform: TransformBundle,
+    pub pose: PhysicsPose,
+}
+
+impl Default for PlayerBoatBundle {
+    fn default() -> Self {
+        Self {
+            player: Player,
+            moving_object: MovingObject,
+            rigid_body: RigidBody::Dynamic,
+            velocity: Velocity {
+                linvel: Vec3::new(0., 10., 0.),
+                ..default()
+            },
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            external_force: ExternalForce::default(),
+            mass: ReadMassProperties::default(),
+            buoyancy: Buoyancy::default(),
+            propulsion: Propulsion::default(),
+            sail: Sail::default(),
+            collider_name: ColliderName("boat".into()),
+            transform: TransformBundle::default(),
+            pose: PhysicsPose::default(),
+        }
+    }
+}
+
+pub fn spawn_player_boat(mut cmd: Commands) {
+    cmd.spawn(PlayerBoatBundle::default());
+}
+
+pub fn player_input_system(
+    actions: Res<ActionState>,
+    time: Res<Time>,
+    mut query: Query<(&mut Propulsion, &mut Sail), With<Player>>,
+) {
+    const TRIM_SPEED: f32 = 0.5;
+
+    let Ok((mut propulsion, mut sail)) = query.get_single_mut() else {
+        return;
+    };
+    propulsion.throttle = actions.axis(Action::Throttle, Action::Reverse);
+    propulsion.rudder = actions.axis(Action::SteerLeft, Action::SteerRight);
+    sail.trim = (sail.trim
+        + actions.axis(Action::EaseOut, Action::SheetIn) * TRIM_SPEED * time.delta_seconds())
+    .clamp(0., 1.);
+}
+
+pub fn apply_propulsion(
+    mut boats: Query<(&Transform, &Velocity, &Propulsion, &mut ExternalForce)>,
+) {
+    for (trans, vel, propulsion, mut force) in boats.iter_mut() {
+        let forward = trans.forward();
+        let thrust = if propulsion.throttle < 0. {
+            propulsion.thrust * propulsion.reverse_ratio
+        } else {
+            propulsion.thrust
+        };
+        force.force += forward * propulsion.throttle * thrust;
+
+        // the rudder only bites when there is water flowing past it, and steers the other way in reverse
+        let speed = vel.linvel.dot(forward);
+        force.torque += trans.up() * propulsion.rudder * propulsion.rudder_torque * speed;
+    }
+}
+
+pub fn add_env_forces(
+    mut floating_objects: Query<
+        (
+            &Transform,
+            &mut Velocity,
+            &mut ExternalForce,
+            &ReadMassProperties,
+            &Buoyancy,
+        ),
+        With<MovingObject>,
+    >,
+    config: Res<Config>,
+    rapier_config: Res<RapierConfiguration>,
+    water: Res<WaterSurface>,
+    sim_time: Res<SimulationTime>,
+    fixed_time: Res<FixedTime>,
+) {
+    let t = sim_time.elapsed;
+    let dt = fixed_time.period.as_secs_f32();
+    for (trans, mut vel, mut force, mass, buoyancy) in floating_objects.iter_mut() {
+        // # bouancy from water
+        // each point carries an equal share of the weight, and is fully submerged at `avg_boat_height`
+        *force = ExternalForce::default();
+        let center_of_mass = trans.transform_point(mass.0.local_center_of_mass);
+        let weight_per_point =
+            mass.0.mass * rapier_config.gravity.length() / buoyancy.points.len().max(1) as f32;
+        for point in buoyancy.points.iter().map(|p| trans.transform_point(*p)) {
+            let depth = water.height_at(point.x, point.z, t) - point.y;
+            let submerged = (depth / config.values.avg_boat_height).clamp(0., 1.);
+            let lift = Vec3::Y * weight_per_point * config.values.floating_c * submerged;
+            *force += ExternalForce::at_point(lift, point, center_of_mass);
+        }
+
+        // # drag from the water, much stronger sideways than forward because of the keel
+        // each coefficient is the rate per second at which the velocity along that local axis decays
+        let drag = Vec3::new(
+            config.values.drag_lateral_c,
+            config.values.drag_vertical_c,
+            config.values.drag_forward_c,
+        );
+        let local_vel = trans.rotation.inverse() * vel.linvel;
+        vel.linvel = trans.rotation * (local_vel * (-drag * dt).exp());
+        vel.angvel *= (-config.values.drag_ang_c * dt).exp();
+    }
+}
+
+pub fn advance_simulation_time(mut sim_time: ResMut<SimulationTime>, fixed_time: Res<FixedTime>) {
+    sim_time.elapsed += fixed_time.period.as_secs_f32();
+}
+
+/// where a body was after the last two physics steps, it is drawn in between them
+///
+/// Only bodies without a parent are moved.
+#[derive(Component, Default)]
+pub struct PhysicsPose {
+    previous: Option<Transform>,
+    current: Option<Transform>,
+    /// the transform the body has, unless another system moved it
+    expected: Option<Transform>,
+}
+
+/// puts bodies back where the last step left them, so the simulation never sees the drawn pose
+pub fn restore_physics_poses(
+    mut bodies: Query<(&mut Transform, &mut GlobalTransform, &mut PhysicsPose), Without<Parent>>,
+) {
+    for (mut trans, mut global, mut pose) in bodies.iter_mut() {
+        match pose.current {
+            Some(current) if pose.expected == Some(*trans) => {
+                trans.translation = current.translation;
+                trans.rotation = current.rotation;
+                // rapier moves the body if the global transform is not the one it wrote
+                *global = GlobalTransform::from(*trans);
+            }
+            // moved by something else, so the body jumps there
+            _ => {
+                pose.previous = None;
+                pose.current = Some(*trans);
+            }
+        }
+    }
+}
+
+pub fn record_physics_poses(mut bodies: Query<(&Transform, &mut PhysicsPose), Without<Parent>>) {
+    for (trans, mut pose) in bodies.iter_mut() {
+        pose.previous = pose.current;
+        pose.current = Some(*trans);
+        pose.expected = Some(*trans);
+    }
+}
+
+/// draws bodies as far between their last two poses as time has passed since the last step
+pub fn interpolate_physics_poses(
+    fixed_time: Res<FixedTime>,
+    mut sim_time: ResMut<SimulationTime>,
+    mut bodies: Query<(&mut Transform, &mut PhysicsPose), Without<Parent>>,
+) {
+    let period = fixed_time.period.as_secs_f32();
+    let overstep = (fixed_time.accumulated().as_secs_f32() / period).min(1.);
+    sim_time.drawn = (sim_time.elapsed - period * (1. - overstep)).max(0.);
+    for (mut trans, mut pose) in bodies.iter_mut() {
+        let (Some(previous), Some(current)) = (pose.previous, pose.current) else {
+            continue;
+        };
+        trans.translation = previous.translation.lerp(current.translation, overstep);
+        trans.rotation = previous.rotation.slerp(current.rotation, overstep);
+        pose.expected = Some(*trans);
+    }
+}
+
+pub fn update_water(
+    config: Res<Config>,
+    mut events: EventReader<ConfigEvent>,
+    mut water: ResMut<WaterSurface>,
+) {
+    for _ in events.iter().filter(|e| e.changed(ConfigSection::Water)) {
+        water.waves = config.values.waves.clone();
+    }
+}
+
+pub fn update_wind(
+    config: Res<Config>,
+    mut events: EventReader<ConfigEvent>,
+    mut wind: ResMut<Wind>,
+) {
+    for _ in events.iter().filter(|e| e.changed(ConfigSection::Wind)) {
+        *wind = config.values.wind;
+    }
+}
+
+/// the colliders are spawned after the config is read, since it decides how boats are decomposed
+///
+/// Without a manifest, `GltfColliderSource` can be inserted by hand instead.
+pub fn spawn_colliders(
+    mut cmd: Commands,
+    config: Res<Config>,
+    assets: Option<Res<ManifestAssets>>,
+) {
+    cmd.insert_resource(config.values.decomposition);
+    if let Some(bboxes) = assets.and_then(|assets| assets.get("bboxes")) {
+        cmd.insert_resource(GltfColliderSource(bboxes));
+    }
+}
+
+/// the mass of a boat made from the glTF, whatever the size of its collider
+const BOAT_MASS: f32 = 500.;
+
+/// gives floating objects the mass and hull points of their collider, once it is made from the glTF
+pub fn setup_hulls(
+    mut cmd: Commands,
+    hulls: Query<(Entity, &Collider, &GltfCollider), (Added<GltfCollider>, With<Buoyancy>)>,
+) {
+    for (entity, collider, info) in hulls.iter() {
+        let aabb = collider.raw.compute_local_aabb();
+        let (min, max) = (Vec3::from(aabb.mins), Vec3::from(aabb.maxs));
+        let mass = match info.shape {
+            // trimeshes have no volume, so the boat gets the mass of its bounding box instead
+            ColliderShape::TriMesh => {
+                ColliderMassProperties::MassProperties(box_mass_properties(min, max, BOAT_MASS))
+            }
+            // the inertia and center of mass follow from the shape
+            _ => ColliderMassProperties::Mass(BOAT_MASS),
+        };
+        cmd.entity(entity).insert((
+            mass,
+            Buoyancy {
+                points: hull_points(min, max, 3, 5),
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{gltf::GltfPlugin, scene::ScenePlugin, time::TimeUpdateStrategy};
+    use std::time::Duration;
+
+    /// the physics without rendering, with the player's boat resting on flat water in calm weather,
+    /// and `frame` between updates
+    fn floating_boat_app(frame: Duration) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ScenePlugin,
+            GltfPlugin::default(),
+            BoatPhysicsPlugin,
+        ))
+        .add_asset::<Mesh>()
+        .add_state::<AssetState>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(frame))
+        .insert_resource(Wind {
+            strength: 0.,
+            ..default()
+        });
+        app.world
+            .resource_mut::<NextState<AssetState>>()
+            .set(AssetState::Loaded);
+        app.update();
+
+        // there is no colliders file, so the boat gets a box instead
+        let (min, max) = (Vec3::new(-1.5, -0.5, -4.), Vec3::new(1.5, 0.5, 4.));
+        let player = app
+            .world
+            .query_filtered::<Entity, With<Player>>()
+            .single(&app.world);
+        app.world.entity_mut(player).insert((
+            Collider::cuboid(max.x, max.y, max.z),
+            ColliderMassProperties::Mass(BOAT_MASS),
+            Velocity::zero(),
+            Buoyancy {
+                points: hull_points(min, max, 3, 5),
+            },
+        ));
+        app
+    }
+
+    /// updates the app until the physics has run for `seconds`
+    fn simulate(app: &mut App, seconds: f32) {
+        while app.world.resource::<SimulationTime>().elapsed < seconds - PHYSICS_DT / 2. {
+            app.update();
+        }
+    }
+
+    /// where the physics left the player's boat, it is drawn a bit behind
+    fn player_pose(app: &mut App) -> Transform {
+        app.world
+            .query::<&PhysicsPose>()
+            .single(&app.world)
+            .current
+            .unwrap()
+    }
+
+    fn push_player(app: &mut App, velocity: Velocity) {
+        *app.world
+            .query_filtered::<&mut Velocity, With<Player>>()
+            .single_mut(&mut app.world) = velocity;
+    }
+
+    #[test]
+    fn boat_floats() {
+        let mut app = floating_boat_app(Duration::from_secs_f32(PHYSICS_DT));
+        simulate(&mut app, 10.);
+        let height = player_pose(&mut app).translation.y;
+        assert!(
+            height.abs() < 1.,
+            "the boat ended up at a height of {height}"
+        );
+    }
+
+    #[test]
+    fn same_motion_at_any_frame_rate() {
+        let sail = |frame: Duration| {
+            // waves and a push, so the boat has something to do
+            let mut app = floating_boat_app(frame);
+            app.world.resource_mut::<WaterSurface>().waves = ConfigValues::default().waves;
+            push_player(
+                &mut app,
+                Velocity {
+                    linvel: Vec3::new(0., 0., -3.),
+                    angvel: Vec3::new(0., 0.5, 0.),
+                },
+            );
+            simulate(&mut app, 3.);
+            let elapsed = app.world.resource::<SimulationTime>().elapsed;
+            let velocity = *app
+                .world
+                .query_filtered::<&Velocity, With<Player>>()
+                .single(&app.world);
+            (elapsed, player_pose(&mut app), velocity)
+        };
+        // two physics steps in every frame, and a step every third frame
+        let step = Duration::from_secs_f32(PHYSICS_DT);
+        let (slow_elapsed, slow, slow_vel) = sail(step * 2);
+        let (fast_elapsed, fast, fast_vel) = sail(step / 3);
+
+        assert_eq!(slow_elapsed, fast_elapsed);
+        assert!(slow.translation.distance(fast.translation) < 1e-3);
+        assert!(slow.rotation.angle_between(fast.rotation) < 1e-3);
+        assert!(slow_vel.linvel.distance(fast_vel.linvel) < 1e-3);
+        assert!(slow_vel.angvel.distance(fast_vel.angvel) < 1e-3);
+        // the boat did move, so the comparison means something
+        assert!(1. < slow.translation.length());
+    }
+
+    #[test]
+    fn draws_between_steps() {
+        // a physics step every fourth frame
+        let mut app = floating_boat_app(Duration::from_secs_f32(PHYSICS_DT) / 4);
+        push_player(&mut app, Velocity::linear(Vec3::new(0., 0., -3.)));
+        simulate(&mut app, 1.);
+
+        let drawn = |app: &mut App| {
+            app.world
+                .query_filtered::<&Transform, With<Player>>()
+                .single(&app.world)
+                .translation
+        };
+        let mut last = drawn(&mut app);
+        for _ in 0..12 {
+            app.update();
+            let now = drawn(&mut app);
+            assert!(now.z < last.z, "the boat stood still from {last} to {now}");
+            assert!(player_pose(&mut app).translation.z <= now.z);
+            last = now;
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{cli::Args, config::*, loading::*, migration::*, storage::*};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde_json::{json, Value};
+
+/// presets are saved here, and shipped ones are in the manifest named `preset/<name>`
+const PRESET_FOLDER: &str = "presets";
+
+/// named sets of config values, so tuning can be kept apart instead of overwriting config.json
+#[derive(Resource, Default)]
+pub struct Presets {
+    names: Vec<String>,
+    /// what to call the current values when saving them as a preset
+    new_name: String,
+    /// the two sides of the comparison, `None` is the current values
+    compare: [Option<String>; 2],
+    /// the values of the compared presets, read when they are chosen
+    compared: [Option<ConfigValues>; 2],
+}
+
+fn preset_file(name: &str) -> String {
+    format!("{PRESET_FOLDER}/{name}.json")
+}
+
+/// the name becomes part of a path, so only characters that can not leave `PRESET_FOLDER` are allowed
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '_' | '-'))
+}
+
+impl Presets {
+    /// finds the shipped and saved presets, a saved one replaces a shipped one with the same name
+    fn refresh(&mut self, storage: &Storage, assets: &ManifestAssets) {
+        let saved = storage.list(PRESET_FOLDER).unwrap_or_else(|e| {
+            warn!("could not list the saved presets: {e}");
+            vec![]
+        });
+        let shipped = assets
+            .names()
+            .filter_map(|name| name.strip_prefix("preset/"));
+        self.names = saved
+            .iter()
+            .filter_map(|file| {
+                file.strip_prefix(&format!("{PRESET_FOLDER}/"))?
+                    .strip_suffix(".json")
+            })
+            .chain(shipped)
+            .map(String::from)
+            .collect();
+        self.names.sort();
+        self.names.dedup();
+    }
+}
+
+fn read_preset(
+    name: &str,
+    storage: &Storage,
+    assets: &ManifestAssets,
+    config_assets: &Assets<ConfigValues>,
+) -> Option<ConfigValues> {
+    if !valid_name(name) {
+        warn!("'{name}' is not a valid preset name");
+        return None;
+    }
+    match storage.read(&preset_file(name)) {
+        Ok(Some(text)) => {
+            return serde_json::from_str(&text)
+                .and_then(|value| serde_json::from_value(migrate_config(value)))
+                .map_err(|e| warn!("the preset '{name}' could not be read: {e}"))
+                .ok();
+        }
+        Ok(None) => {}
+        Err(e) => warn!("could not read the preset '{name}': {e}"),
+    }
+    assets
+        .get::<ConfigValues>(&format!("preset/{name}"))
+        .and_then(|handle| config_assets.get(&handle).cloned())
+}
+
+/// every value in a json tree, with the path to it like `wind.strength`
+fn flatten(value: &Value, path: String, out: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                flatten(value, path, out);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                flatten(value, format!("{path}[{i}]"), out);
+            }
+        }
+        value => out.push((path, value.to_string())),
+    }
+}
+
+/// the values that differ between two configs, as `(path, a, b)`
+fn diff_values(a: &ConfigValues, b: &ConfigValues) -> Vec<(String, String, String)> {
+    let (mut a_values, mut b_values) = (vec![], vec![]);
+    flatten(&json!(a), String::new(), &mut a_values);
+    flatten(&json!(b), String::new(), &mut b_values);
+    // the configs can have a different number of waves, so the values are matched by path
+    let missing = || "-".to_owned();
+    let mut differences: Vec<_> = a_values
+        .iter()
+        .map(|(path, a)| {
+            let b = b_values.iter().find(|(p, _)| p == path);
+            (
+                path.clone(),
+                a.clone(),
+                b.map_or_else(missing, |(_, b)| b.clone()),
+            )
+        })
+        .collect();
+    differences.extend(
+        b_values
+            .into_iter()
+            .filter(|(path, _)| !a_values.iter().any(|(p, _)| p == path))
+            .map(|(path, b)| (path, missing(), b)),
+    );
+    differences.retain(|(_, a, b)| a != b);
+    differences
+}
+
+/// uses the preset given with `--preset` instead of the config file
+pub fn apply_preset_arg(
+    mut config: ResMut<Config>,
+    mut writer: EventWriter<ConfigEvent>,
+    args: Res<Args>,
+    storage: Res<Storage>,
+    assets: Res<ManifestAssets>,
+    config_assets: Res<Assets<ConfigValues>>,
+) {
+    let Some(name) = &args.preset else {
+        return;
+    };
+    let Some(values) = read_preset(name, &storage, &assets, &config_assets) else {
+        error!("there is no preset named '{name}', the config is used instead");
+        return;
+    };
+    info!("using the preset '{name}'");
+    let changes = config.values.diff(&values);
+    config.values = values;
+    config.saved = false;
+    writer.send(ConfigEvent::Changed(changes));
+}
+
+pub fn presets_ui(
+    mut contexts: EguiContexts,
+    mut config: ResMut<Config>,
+    mut presets: ResMut<Presets>,
+    mut writer: EventWriter<ConfigEvent>,
+    storage: Res<Storage>,
+    assets: Res<ManifestAssets>,
+    config_assets: Res<Assets<ConfigValues>>,
+) {
+    use egui::*;
+
+    if presets.is_added() {
+        presets.refresh(&storage, &assets);
+    }
+    let presets = presets.as_mut();
+
+    Window::new("presets").show(contexts.ctx_mut(), |ui| {
+        let mut to_load = None;
+        ComboBox::from_label("load preset")
+            .selected_text("choose a preset")
+            .show_ui(ui, |ui| {
+                for name in presets.names.iter() {
+                    if ui.selectable_label(false, name).clicked() {
+                        to_load = Some(name.clone());
+                    }
+                }
+            });
+        if let Some(values) =
+            to_load.and_then(|name| read_preset(&name, &storage, &assets, &config_assets))
+        {
+            let changes = config.values.diff(&values);
+            config.values = values;
+            config.saved = false;
+            writer.send(ConfigEvent::Changed(changes));
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut presets.new_name);
+            let name = presets.new_name.trim().to_owned();
+            if ui
+                .add_enabled(valid_name(&name), Button::new("save as preset"))
+                .on_disabled_hover_text("use only letters, digits, spaces, _ and -")
+                .clicked()
+            {
+                match storage.write(&preset_file(&name), &json!(config.values).to_string()) {
+                    Ok(_) => presets.refresh(&storage, &assets),
+                    Err(e) => error!("could not save the preset '{name}': {e}"),
+                }
+            }
+        });
+
+        ui.separator();
+        ui.label("compare");
+        let side_name = |side: &Option<String>| side.clone().unwrap_or("current".into());
+        let mut sides = presets.compare.clone();
+        for (i, side) in sides.iter_mut().enumerate() {
+            ComboBox::from_id_source(("compare", i))
+                .selected_text(side_name(side))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(side, None, "current");
+                    for name in presets.names.iter() {
+                        ui.selectable_value(side, Some(name.clone()), name);
+                    }
+                });
+        }
+        if sides != presets.compare {
+            presets.compared = sides.clone().map(|side| {
+                side.and_then(|name| read_preset(&name, &storage, &assets, &config_assets))
+            });
+            presets.compare = sides;
+        }
+
+        let values = [0, 1].map(|i| match presets.compare[i] {
+            None => Some(&config.values),
+            Some(_) => presets.compared[i].as_ref(),
+        });
+        let [Some(a), Some(b)] = values else {
+            ui.label("the preset could not be read");
+            return;
+        };
+        let differences = diff_values(a, b);
+        if differences.is_empty() {
+            ui.label("no differences");
+            return;
+        }
+        Grid::new("preset differences")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label(side_name(&presets.compare[0]));
+                ui.label(side_name(&presets.compare[1]));
+                ui.end_row();
+                for (path, a, b) in differences {
+                    ui.label(path);
+                    ui.label(a);
+                    ui.label(b);
+                    ui.end_row();
+                }
+            });
+    });
+}
@@ -0,0 +1,132 @@
+use crate::utils::SimulationTime;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+const AIR_DENSITY: f32 = 1.225;
+
+/// the true wind, which varies over time with gusts
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+#[serde(default)]
+pub struct Wind {
+    /// direction the wind blows towards in radians, measured from the x axis towards the z axis
+    pub direction: f32,
+    /// average wind speed
+    pub strength: f32,
+    /// how much the wind speed varies, as a fraction of `strength`
+    pub gust: f32,
+    /// seconds between the strongest gusts
+    pub gust_period: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: 0.,
+            strength: 8.,
+            gust: 0.3,
+            gust_period: 12.,
+        }
+    }
+}
+
+impl Wind {
+    pub fn velocity_at(&self, t: f32) -> Vec3 {
+        let phase = TAU * t / self.gust_period.max(0.1);
+        let gust = 0.6 * phase.sin() + 0.4 * (2.7 * phase).sin();
+        let speed = self.strength * (1. + self.gust * gust);
+        // the wind also veers a little during gusts
+        let direction = self.direction + 0.1 * self.gust * (1.3 * phase).cos();
+        Vec3::new(direction.cos(), 0., direction.sin()) * speed
+    }
+}
+
+/// a sail that turns the apparent wind into thrust
+#[derive(Component)]
+pub struct Sail {
+    /// from 0, sheeted all the way in, to 1, eased all the way out
+    pub trim: f32,
+    pub area: f32,
+    /// the sail luffs when pointing closer than this to the apparent wind, in radians
+    pub no_go_angle: f32,
+    /// local point where the sail force acts, high above the hull so the boat heels
+    pub center_of_effort: Vec3,
+}
+
+impl Default for Sail {
+    fn default() -> Self {
+        Self {
+            trim: 0.5,
+            area: 40.,
+            no_go_angle: 0.6,
+            center_of_effort: Vec3::new(0., 3., 0.),
+        }
+    }
+}
+
+impl Sail {
+    /// angle between the boom and the centerline, the boom is always on the leeward side
+    pub fn boom_angle(&self) -> f32 {
+        self.trim.clamp(0., 1.) * FRAC_PI_2
+    }
+
+    /// force on the sail, given the apparent wind and the direction of the bow
+    pub fn force(&self, apparent_wind: Vec3, forward: Vec3) -> Vec3 {
+        let Some(wind_dir) = apparent_wind.try_normalize() else {
+            return Vec3::ZERO;
+        };
+        // 0 is head to wind, PI is dead downwind
+        let wind_angle = (-wind_dir).angle_between(forward);
+        if wind_angle < self.no_go_angle {
+            return Vec3::ZERO;
+        }
+
+        // a sail eased past the wind just flaps
+        let attack = wind_angle - self.boom_angle();
+        if attack < 0. {
+            return Vec3::ZERO;
+        }
+        let attack = if attack > FRAC_PI_2 {
+            std::f32::consts::PI - attack
+        } else {
+            attack
+        };
+
+        // flat plate coefficients; lift peaks at 45 degrees, drag grows until the sail is square to the wind
+        let lift_c = (2. * attack).sin();
+        let drag_c = 2. * attack.sin().powi(2);
+        let pressure = 0.5 * AIR_DENSITY * apparent_wind.length_squared() * self.area;
+
+        // lift is across the apparent wind, on the side the bow points to
+        let lift_dir = (forward - wind_dir * forward.dot(wind_dir)).normalize_or_zero();
+        (lift_dir * lift_c + wind_dir * drag_c) * pressure
+    }
+}
+
+pub fn apply_sail_forces(
+    sim_time: Res<SimulationTime>,
+    wind: Res<Wind>,
+    mut boats: Query<(
+        &Transform,
+        &Velocity,
+        &Sail,
+        &ReadMassProperties,
+        &mut ExternalForce,
+    )>,
+) {
+    let true_wind = wind.velocity_at(sim_time.elapsed);
+    for (trans, vel, sail, mass, mut force) in boats.iter_mut() {
+        let apparent_wind = true_wind - Vec3::new(vel.linvel.x, 0., vel.linvel.z);
+        let forward = trans.forward();
+        let forward = Vec3::new(forward.x, 0., forward.z).normalize_or_zero();
+
+        let center_of_mass = trans.transform_point(mass.0.local_center_of_mass);
+        let center_of_effort = trans.transform_point(sail.center_of_effort);
+        *force += ExternalForce::at_point(
+            sail.force(apparent_wind, forward),
+            center_of_effort,
+            center_of_mass,
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use std::{collections::HashMap, fmt, io, sync::Mutex};
+
+/// somewhere to keep small text files, like the config, between runs
+pub trait StorageBackend: Send + Sync {
+    /// the saved text, or `None` if nothing is saved with this name
+    fn read(&self, name: &str) -> Result<Option<String>, StorageError>;
+    fn write(&self, name: &str, text: &str) -> Result<(), StorageError>;
+    /// the names of everything saved in a folder, including the folder
+    fn list(&self, folder: &str) -> Result<Vec<String>, StorageError>;
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    /// the browser has no local storage, or refused to store the value
+    Browser,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "{e}"),
+            StorageError::Browser => write!(f, "the browser local storage is not available"),
+        }
+    }
+}
+
+/// saves files next to the shipped assets, so saved tuning can be committed
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    pub root: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StorageBackend for FileStorage {
+    fn read(&self, name: &str) -> Result<Option<String>, StorageError> {
+        match std::fs::read_to_string(self.root.join(name)) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    fn write(&self, name: &str, text: &str) -> Result<(), StorageError> {
+        let path = self.root.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(StorageError::Io)?;
+        }
+        std::fs::write(path, text).map_err(StorageError::Io)
+    }
+
+    fn list(&self, folder: &str) -> Result<Vec<String>, StorageError> {
+        let entries = match std::fs::read_dir(self.root.join(folder)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(StorageError::Io(e)),
+        };
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| format!("{folder}/{}", entry.file_name().to_string_lossy()))
+            .collect())
+    }
+}
+
+/// saves files in the browser, since the web build can not write to the assets
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage {
+    /// keeps the keys apart from other pages on the same site
+    pub prefix: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    fn storage() -> Result<web_sys::Storage, StorageError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or(StorageError::Browser)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl StorageBackend for LocalStorage {
+    fn read(&self, name: &str) -> Result<Option<String>, StorageError> {
+        Self::storage()?
+            .get_item(&(self.prefix.clone() + name))
+            .map_err(|_| StorageError::Browser)
+    }
+
+    fn write(&self, name: &str, text: &str) -> Result<(), StorageError> {
+        Self::storage()?
+            .set_item(&(self.prefix.clone() + name), text)
+            .map_err(|_| StorageError::Browser)
+    }
+
+    fn list(&self, folder: &str) -> Result<Vec<String>, StorageError> {
+        let storage = Self::storage()?;
+        let len = storage.length().map_err(|_| StorageError::Browser)?;
+        let folder = format!("{}{folder}/", self.prefix);
+        Ok((0..len)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter(|key| key.starts_with(&folder))
+            .map(|key| key[self.prefix.len()..].to_owned())
+            .collect())
+    }
+}
+
+/// forgets everything when the program stops, for running without touching any files
+#[derive(Default)]
+pub struct MemoryStorage {
+    files: Mutex<HashMap<String, String>>,
+}
+
+impl StorageBackend for MemoryStorage {
+    fn read(&self, name: &str) -> Result<Option<String>, StorageError> {
+        Ok(self.files.lock().unwrap().get(name).cloned())
+    }
+
+    fn write(&self, name: &str, text: &str) -> Result<(), StorageError> {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), text.to_owned());
+        Ok(())
+    }
+
+    fn list(&self, folder: &str) -> Result<Vec<String>, StorageError> {
+        let folder = format!("{folder}/");
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|name| name.starts_with(&folder))
+            .cloned()
+            .collect())
+    }
+}
+
+/// where the config and other save data go, the files on native and local storage on the web
+#[derive(Resource)]
+pub struct Storage(pub Box<dyn StorageBackend>);
+
+impl Default for Storage {
+    fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let backend = FileStorage {
+            root: "assets".into(),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let backend = LocalStorage {
+            prefix: "seilespill/".into(),
+        };
+        Self(Box::new(backend))
+    }
+}
+
+impl Storage {
+    pub fn read(&self, name: &str) -> Result<Option<String>, StorageError> {
+        self.0.read(name)
+    }
+
+    pub fn write(&self, name: &str, text: &str) -> Result<(), StorageError> {
+        self.0.write(name, text)
+    }
+
+    pub fn list(&self, folder: &str) -> Result<Vec<String>, StorageError> {
+        self.0.list(folder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> Storage {
+        Storage(Box::new(MemoryStorage::default()))
+    }
+
+    #[test]
+    fn reads_what_was_written() {
+        let storage = memory();
+        assert_eq!(storage.read("config.json").unwrap(), None);
+
+        storage.write("config.json", "{}").unwrap();
+        storage.write("config.json", "{\"version\":1}").unwrap();
+        assert_eq!(
+            storage.read("config.json").unwrap().as_deref(),
+            Some("{\"version\":1}")
+        );
+    }
+
+    #[test]
+    fn lists_only_the_folder() {
+        let storage = memory();
+        assert!(storage.list("presets").unwrap().is_empty());
+
+        storage.write("presets/calm.json", "{}").unwrap();
+        storage.write("presets/storm.json", "{}").unwrap();
+        storage.write("presets-old/calm.json", "{}").unwrap();
+        storage.write("config.json", "{}").unwrap();
+        let mut names = storage.list("presets").unwrap();
+        names.sort();
+        assert_eq!(names, ["presets/calm.json", "presets/storm.json"]);
+    }
+}
@@ -0,0 +1,453 @@
+use crate::{custom_assets::JsonAssetPlugin, dock::*, loading::*, physics::Player};
+use bevy::{ecs::query::Has, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Buying and selling goods at ports, with the ports from the manifest's `ports` entry.
+///
+/// The player gets `Cargo` and `Money`, and each dock with a port in the file gets a `Market`
+/// and the `DockCards` of its traders. The trades are chosen in the menu of `DockingPlugin`.
+pub struct TradePlugin;
+
+impl Plugin for TradePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<PortCatalog>::new(&["ports.json"]))
+            .add_event::<DockEvent>()
+            .add_event::<TradeEvent>()
+            .init_resource::<PlayerData>()
+            .init_resource::<TradeLog>()
+            .add_systems(Update, outfit_player)
+            .add_systems(
+                Update,
+                (
+                    open_markets,
+                    greet_at_port,
+                    execute_trades.after(greet_at_port),
+                    update_trade_status.after(execute_trades),
+                )
+                    .run_if(in_state(AssetState::Loaded)),
+            );
+    }
+}
+
+/// every port in the game, keyed by the dock they are at
+#[derive(Serialize, Deserialize, Clone, bevy::reflect::TypeUuid, bevy::reflect::TypePath)]
+#[uuid = "2b9e7f31-8c4d-4a5e-b6f0-3d1a9c7e5b24"]
+pub struct PortCatalog {
+    pub ports: Vec<Port>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Port {
+    /// the id of the `Dock` the port is at
+    pub dock: String,
+    pub name: String,
+    pub goods: Vec<MarketGood>,
+    /// the people on the dock, each offering one trade
+    pub traders: Vec<Trader>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MarketGood {
+    pub name: String,
+    /// what the port pays for each one
+    pub buy_price: u32,
+    /// what the port asks for each one
+    pub sell_price: u32,
+    pub stock: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeKind {
+    /// the player buys from the port
+    Buy,
+    /// the player sells to the port
+    Sell,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Trader {
+    pub name: String,
+    pub kind: TradeKind,
+    pub good: String,
+    /// how many change hands in one trade
+    pub amount: u32,
+}
+
+impl Trader {
+    /// what the whole trade costs at the prices of `good`, `None` if it is too large to count
+    pub fn price(&self, good: &MarketGood) -> Option<u32> {
+        match self.kind {
+            TradeKind::Buy => good.sell_price,
+            TradeKind::Sell => good.buy_price,
+        }
+        .checked_mul(self.amount)
+    }
+}
+
+/// what a port buys and sells, and how much it has left, on the entity with its `Dock`
+#[derive(Component, Clone, Debug)]
+pub struct Market {
+    pub port: Port,
+}
+
+impl Market {
+    pub fn good(&self, name: &str) -> Option<&MarketGood> {
+        self.port.goods.iter().find(|good| good.name == name)
+    }
+
+    /// what the trader would say, with the price at this port
+    pub fn offer(&self, trader: &Trader) -> String {
+        let Some(good) = self.good(&trader.good) else {
+            return format!("{} is not traded here", trader.good);
+        };
+        let Some(price) = trader.price(good) else {
+            return format!(
+                "{} {} is more than anyone can pay",
+                trader.amount, trader.good
+            );
+        };
+        match trader.kind {
+            TradeKind::Buy => format!(
+                "Do you want to buy {} {} for {price}?",
+                trader.amount, trader.good
+            ),
+            TradeKind::Sell => format!("I pay {price} for {} {}.", trader.amount, trader.good),
+        }
+    }
+
+    /// a card for each trader, with the deal they offer at `dock`
+    pub fn cards(&self, dock: Entity) -> Vec<Card> {
+        self.port
+            .traders
+            .iter()
+            .enumerate()
+            .map(|(i, trader)| Card {
+                person_name: trader.name.clone(),
+                task: self.offer(trader),
+                deal: Some(Deal { dock, trader: i }),
+            })
+            .collect()
+    }
+}
+
+/// the goods carried by a boat
+#[derive(Component, Debug)]
+pub struct Cargo {
+    pub goods: HashMap<String, u32>,
+    /// how many goods fit in the hold, of any kind
+    pub capacity: u32,
+}
+
+impl Default for Cargo {
+    fn default() -> Self {
+        Self {
+            goods: HashMap::new(),
+            capacity: 50,
+        }
+    }
+}
+
+impl Cargo {
+    pub fn load(&self) -> u32 {
+        self.goods.values().sum()
+    }
+
+    pub fn count(&self, good: &str) -> u32 {
+        self.goods.get(good).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Money(pub u32);
+
+impl Default for Money {
+    fn default() -> Self {
+        Self(100)
+    }
+}
+
+/// how the last trade went, shown in the dock menu
+#[derive(Resource, Default)]
+pub struct TradeLog {
+    pub last: String,
+}
+
+/// gives a new player an empty hold and some money, unless it already has them
+pub fn outfit_player(
+    mut cmd: Commands,
+    players: Query<(Entity, Has<Cargo>, Has<Money>), Added<Player>>,
+) {
+    for (entity, has_cargo, has_money) in players.iter() {
+        if !has_cargo {
+            cmd.entity(entity).insert(Cargo::default());
+        }
+        if !has_money {
+            cmd.entity(entity).insert(Money::default());
+        }
+    }
+}
+
+/// gives each dock the market of its port, once both are loaded
+pub fn open_markets(
+    mut cmd: Commands,
+    docks: Query<(Entity, &Dock), Without<Market>>,
+    assets: Res<ManifestAssets>,
+    catalogs: Res<Assets<PortCatalog>>,
+) {
+    if docks.is_empty() {
+        return;
+    }
+    let Some(catalog) = assets
+        .get::<PortCatalog>("ports")
+        .and_then(|handle| catalogs.get(&handle))
+    else {
+        return;
+    };
+    for (entity, dock) in docks.iter() {
+        let market = match catalog.ports.iter().find(|port| port.dock == dock.id) {
+            Some(port) => {
+                debug!("opening the market at '{}'", port.name);
+                Market { port: port.clone() }
+            }
+            None => {
+                warn!("there is no port for the dock '{}'", dock.id);
+                // an empty market, so the dock is not looked up again
+                Market {
+                    port: Port {
+                        dock: dock.id.clone(),
+                        name: dock.id.clone(),
+                        goods: vec![],
+                        traders: vec![],
+                    },
+                }
+            }
+        };
+        cmd.entity(entity)
+            .insert((DockCards(market.cards(entity)), market));
+    }
+}
+
+/// starts the trade log over with the name of the port, when the player docks
+pub fn greet_at_port(
+    mut dock_reader: EventReader<DockEvent>,
+    player_data: Res<PlayerData>,
+    markets: Query<&Market>,
+    mut log: ResMut<TradeLog>,
+) {
+    for event in dock_reader.iter() {
+        let (DockEvent::Docking, DockState::DockedTo(dock)) = (event, &player_data.dock_state)
+        else {
+            continue;
+        };
+        log.last = match markets.get(*dock) {
+            Ok(market) => format!("welcome to {}", market.port.name),
+            Err(_) => String::new(),
+        };
+    }
+}
+
+/// moves goods and money between the player and the market, if both sides can afford it
+pub fn execute_trades(
+    mut events: EventReader<TradeEvent>,
+    mut markets: Query<&mut Market>,
+    mut player: Query<(&mut Cargo, &mut Money), With<Player>>,
+    mut log: ResMut<TradeLog>,
+) {
+    for TradeEvent(deal) in events.iter() {
+        let Ok((mut cargo, mut money)) = player.get_single_mut() else {
+            continue;
+        };
+        let Ok(mut market) = markets.get_mut(deal.dock) else {
+            continue;
+        };
+        let Some(trader) = market.port.traders.get(deal.trader).cloned() else {
+            continue;
+        };
+        let Some(good) = market
+            .port
+            .goods
+            .iter_mut()
+            .find(|good| good.name == trader.good)
+        else {
+            log.last = format!("{} is not traded here", trader.good);
+            continue;
+        };
+
+        let amount = trader.amount;
+        let Some(price) = trader.price(good) else {
+            log.last = format!("{amount} {} is more than anyone can pay", good.name);
+            warn!(
+                "the price of {amount} {} at {} is too large",
+                good.name, trader.name
+            );
+            continue;
+        };
+        log.last = match trader.kind {
+            TradeKind::Buy => {
+                if good.stock < amount {
+                    format!("{} has no more {}", trader.name, good.name)
+                } else if money.0 < price {
+                    format!("you need {price} to buy {amount} {}", good.name)
+                } else if cargo.capacity < cargo.load().saturating_add(amount) {
+                    format!("there is no room for {amount} {}", good.name)
+                } else {
+                    good.stock -= amount;
+                    money.0 -= price;
+                    *cargo.goods.entry(good.name.clone()).or_default() += amount;
+                    format!("bought {amount} {} for {price}", good.name)
+                }
+            }
+            TradeKind::Sell => {
+                if cargo.count(&good.name) < amount {
+                    format!("you have no {amount} {} to sell", good.name)
+                } else if money.0.checked_add(price).is_none() {
+                    format!("you can not carry more than {} money", u32::MAX)
+                } else if good.stock.checked_add(amount).is_none() {
+                    format!("{} has no room for more {}", trader.name, good.name)
+                } else {
+                    good.stock += amount;
+                    money.0 += price;
+                    *cargo.goods.entry(good.name.clone()).or_default() -= amount;
+                    cargo.goods.retain(|_, count| 0 < *count);
+                    format!("sold {amount} {} for {price}", good.name)
+                }
+            }
+        };
+        info!("{}", log.last);
+    }
+}
+
+pub fn update_trade_status(
+    mut status: Query<&mut Text, With<DockStatus>>,
+    player: Query<(&Cargo, &Money), With<Player>>,
+    log: Res<TradeLog>,
+) {
+    let Ok((cargo, money)) = player.get_single() else {
+        return;
+    };
+    let mut goods: Vec<_> = cargo
+        .goods
+        .iter()
+        .map(|(name, count)| format!("{count} {name}"))
+        .collect();
+    goods.sort();
+    let cargo_text = if goods.is_empty() {
+        "nothing".into()
+    } else {
+        goods.join(", ")
+    };
+    for mut text in status.iter_mut() {
+        let Some(section) = text.sections.first_mut() else {
+            continue;
+        };
+        let value = format!(
+            "money: {}   cargo ({}/{}): {cargo_text}\n{}",
+            money.0,
+            cargo.load(),
+            cargo.capacity,
+            log.last
+        );
+        if section.value != value {
+            section.value = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trader(kind: TradeKind, amount: u32) -> Trader {
+        Trader {
+            name: "trader".into(),
+            kind,
+            good: "rum".into(),
+            amount,
+        }
+    }
+
+    /// a port selling and buying rum, and the player with nothing in the hold
+    fn trade_app() -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), TradePlugin))
+            .add_state::<AssetState>();
+        let catalog = PortCatalog {
+            ports: vec![Port {
+                dock: "test".into(),
+                name: "Test Port".into(),
+                goods: vec![MarketGood {
+                    name: "rum".into(),
+                    buy_price: 8,
+                    sell_price: 10,
+                    stock: 20,
+                }],
+                traders: vec![
+                    trader(TradeKind::Buy, 5),
+                    trader(TradeKind::Sell, 5),
+                    trader(TradeKind::Buy, u32::MAX),
+                ],
+            }],
+        };
+        let handle = app.world.resource_mut::<Assets<PortCatalog>>().add(catalog);
+        app.insert_resource(ManifestAssets::new(vec![(
+            ManifestEntry {
+                name: "ports".into(),
+                path: "test.ports.json".into(),
+                vital: false,
+            },
+            handle.clone_untyped(),
+        )]));
+        let dock = app.world.spawn(Dock { id: "test".into() }).id();
+        let player = app.world.spawn(Player).id();
+        app.world
+            .resource_mut::<NextState<AssetState>>()
+            .set(AssetState::Loaded);
+        app.update();
+        app.update();
+        (app, dock, player)
+    }
+
+    fn trade(app: &mut App, dock: Entity, trader: usize) {
+        app.world.send_event(TradeEvent(Deal { dock, trader }));
+        app.update();
+    }
+
+    fn money_and_rum(app: &App, player: Entity) -> (u32, u32) {
+        let player = app.world.entity(player);
+        (
+            player.get::<Money>().unwrap().0,
+            player.get::<Cargo>().unwrap().count("rum"),
+        )
+    }
+
+    #[test]
+    fn buys_and_sells() {
+        let (mut app, dock, player) = trade_app();
+        assert_eq!(
+            app.world.get::<DockCards>(dock).map(|cards| cards.0.len()),
+            Some(3)
+        );
+        assert_eq!(money_and_rum(&app, player), (100, 0));
+
+        trade(&mut app, dock, 0);
+        assert_eq!(money_and_rum(&app, player), (50, 5));
+        trade(&mut app, dock, 1);
+        assert_eq!(money_and_rum(&app, player), (90, 0));
+        // there is nothing left to sell
+        trade(&mut app, dock, 1);
+        assert_eq!(money_and_rum(&app, player), (90, 0));
+    }
+
+    #[test]
+    fn rejects_prices_too_large_to_count() {
+        let (mut app, dock, player) = trade_app();
+        trade(&mut app, dock, 2);
+        assert_eq!(money_and_rum(&app, player), (100, 0));
+        assert!(app
+            .world
+            .resource::<TradeLog>()
+            .last
+            .contains("more than anyone can pay"));
+    }
+}
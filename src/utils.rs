@@ -1,5 +1,45 @@
-use bevy::prelude::Vec3;
+use bevy::prelude::{Resource, Vec3};
+use bevy_rapier3d::prelude::MassProperties;
+
+/// seconds simulated by the fixed physics steps, used instead of `Time` so forces don't depend on the frame rate
+#[derive(Resource, Default)]
+pub struct SimulationTime {
+    pub elapsed: f32,
+    /// the time between the last two steps that the bodies are drawn at, for anything drawn along with them
+    pub drawn: f32,
+}
 
 pub fn length_xz(value: &Vec3) -> f32 {
     (value.x * value.x + value.z * value.z).sqrt()
 }
+
+/// spreads `nx` by `nz` points evenly across the bottom of a bounding box
+pub fn hull_points(min: Vec3, max: Vec3, nx: usize, nz: usize) -> Vec<Vec3> {
+    let spread = |a: f32, b: f32, i: usize, n: usize| match n {
+        0 | 1 => (a + b) / 2.,
+        n => a + (b - a) * i as f32 / (n - 1) as f32,
+    };
+    (0..nx)
+        .flat_map(|i| {
+            (0..nz).map(move |j| {
+                Vec3::new(
+                    spread(min.x, max.x, i, nx),
+                    min.y,
+                    spread(min.z, max.z, j, nz),
+                )
+            })
+        })
+        .collect()
+}
+
+/// mass properties of a solid box filling the bounding box, useful for shapes without volume like trimeshes
+pub fn box_mass_properties(min: Vec3, max: Vec3, mass: f32) -> MassProperties {
+    let size = max - min;
+    let sq = size * size;
+    MassProperties {
+        local_center_of_mass: (min + max) / 2.,
+        mass,
+        principal_inertia: Vec3::new(sq.y + sq.z, sq.x + sq.z, sq.x + sq.y) * mass / 12.,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,141 @@
+use crate::utils::SimulationTime;
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+const GRAVITY: f32 = 9.81;
+
+/// a single sine wave travelling across the water
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+#[serde(default)]
+pub struct Wave {
+    pub amplitude: f32,
+    pub wavelength: f32,
+    /// direction of travel in radians, measured from the x axis towards the z axis
+    pub direction: f32,
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.3,
+            wavelength: 20.,
+            direction: 0.,
+        }
+    }
+}
+
+impl Wave {
+    fn wave_number(&self) -> f32 {
+        TAU / self.wavelength.max(0.01)
+    }
+
+    fn dir(&self) -> Vec2 {
+        Vec2::new(self.direction.cos(), self.direction.sin())
+    }
+
+    /// phase at a point; the speed follows the dispersion of deep water waves
+    fn phase(&self, x: f32, z: f32, t: f32) -> f32 {
+        let k = self.wave_number();
+        k * self.dir().dot(Vec2::new(x, z)) - (GRAVITY * k).sqrt() * t
+    }
+}
+
+/// the water as a sum of sines, shared by physics and the ocean mesh so they agree on where the water is
+#[derive(Resource, Default, Clone)]
+pub struct WaterSurface {
+    pub waves: Vec<Wave>,
+}
+
+impl WaterSurface {
+    pub fn height_at(&self, x: f32, z: f32, t: f32) -> f32 {
+        self.waves
+            .iter()
+            .map(|w| w.amplitude * w.phase(x, z, t).sin())
+            .sum()
+    }
+
+    pub fn normal_at(&self, x: f32, z: f32, t: f32) -> Vec3 {
+        let slope = self
+            .waves
+            .iter()
+            .map(|w| w.dir() * w.amplitude * w.wave_number() * w.phase(x, z, t).cos())
+            .sum::<Vec2>();
+        Vec3::new(-slope.x, 1., -slope.y).normalize()
+    }
+}
+
+/// marks the root of the ocean scene, meshes below it follow the `WaterSurface`
+#[derive(Component)]
+pub struct Ocean;
+
+/// a mesh under `Ocean` along with its vertices before any waves were applied
+#[derive(Component)]
+pub struct OceanMesh {
+    rest_positions: Vec<[f32; 3]>,
+}
+
+pub fn tag_ocean_meshes(
+    mut cmd: Commands,
+    meshes: Query<(Entity, &Handle<Mesh>), Added<Handle<Mesh>>>,
+    parents: Query<&Parent>,
+    ocean: Query<(), With<Ocean>>,
+    mut assets_mesh: ResMut<Assets<Mesh>>,
+) {
+    for (entity, handle) in meshes.iter() {
+        if !parents.iter_ancestors(entity).any(|e| ocean.contains(e)) {
+            continue;
+        }
+        // the mesh is modified every frame, so make sure no other entity shares it
+        let Some(mesh) = assets_mesh.get(handle).cloned() else {
+            continue;
+        };
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION).cloned()
+        else {
+            warn!("ocean mesh has no vertex positions, it will stay flat");
+            continue;
+        };
+        cmd.entity(entity).insert((
+            assets_mesh.add(mesh),
+            OceanMesh {
+                rest_positions: positions,
+            },
+        ));
+    }
+}
+
+pub fn deform_ocean(
+    sim_time: Res<SimulationTime>,
+    water: Res<WaterSurface>,
+    ocean_meshes: Query<(&Handle<Mesh>, &GlobalTransform, &OceanMesh)>,
+    mut assets_mesh: ResMut<Assets<Mesh>>,
+) {
+    // the same time the boats are drawn at, so they ride the waves they are drawn on
+    let t = sim_time.drawn;
+    for (handle, global, ocean_mesh) in ocean_meshes.iter() {
+        let Some(mesh) = assets_mesh.get_mut(handle) else {
+            continue;
+        };
+        let to_local = global.affine().inverse();
+        // normals go back with the transpose, so they stay correct for scaled meshes
+        let normal_to_local = global.affine().matrix3.transpose();
+        let (positions, normals): (Vec<[f32; 3]>, Vec<[f32; 3]>) = ocean_mesh
+            .rest_positions
+            .iter()
+            .map(|p| {
+                let world = global.transform_point(Vec3::from(*p));
+                let height = water.height_at(world.x, world.z, t);
+                let normal = water.normal_at(world.x, world.z, t);
+                (
+                    to_local
+                        .transform_point3(Vec3::new(world.x, height, world.z))
+                        .to_array(),
+                    normal_to_local.mul_vec3(normal).normalize().to_array(),
+                )
+            })
+            .unzip();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    }
+}